@@ -0,0 +1,250 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::models::{ChatMessage, ChatRequest, ChatResponse, ModelInfo, Usage};
+use crate::api::retry;
+use crate::api::sse::SseStream;
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::{check_status, ProviderError};
+
+/// Provider name reported in errors
+const PROVIDER: &str = "anthropic";
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const MESSAGES_PATH: &str = "/messages";
+const MODELS_PATH: &str = "/models";
+/// Largest page `/v1/models` accepts
+const MODELS_PAGE_SIZE: u32 = 1000;
+const API_VERSION: &str = "2023-06-01";
+const DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
+/// The Messages API requires `max_tokens` on every request
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic Messages API (`provider = "anthropic"`).
+pub struct Anthropic;
+
+#[async_trait]
+impl ChatProvider for Anthropic {
+    fn name(&self) -> &'static str { "anthropic" }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: true, requires_base_url: false, pricing: false }
+    }
+
+    fn api_key_env(&self) -> Option<&'static str> { Some("ANTHROPIC_API_KEY") }
+
+    fn default_base_url(&self) -> Option<&'static str> { Some(DEFAULT_BASE_URL) }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        chat_complete(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?, req).await
+    }
+
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        chat_complete_stream(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?, req, on_chunk).await
+    }
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        list_models(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+impl MessagesRequest {
+    /// Lift `system` role messages into the top-level `system` field; the
+    /// Messages API only accepts `user`/`assistant` turns in `messages`.
+    /// `seed` and the penalties have no Anthropic equivalent and are dropped.
+    fn from_chat(req: ChatRequest) -> Self {
+        let (system, messages): (Vec<ChatMessage>, Vec<ChatMessage>) =
+            req.messages.into_iter().partition(|m| m.role == "system");
+        let system = if system.is_empty() {
+            None
+        } else {
+            Some(system.into_iter().map(|m| m.content).collect::<Vec<_>>().join("\n\n"))
+        };
+        let model = if req.model.is_empty() { DEFAULT_MODEL.to_string() } else { req.model };
+        let p = req.params;
+        Self {
+            model,
+            max_tokens: p.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system,
+            messages,
+            stream: None,
+            temperature: p.temperature,
+            top_p: p.top_p,
+            stop_sequences: p.stop,
+        }
+    }
+}
+
+fn build_headers(api_key: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-api-key", HeaderValue::from_str(api_key)?);
+    headers.insert("anthropic-version", HeaderValue::from_static(API_VERSION));
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(headers)
+}
+
+fn build_endpoint(base_url: &str, path: &str) -> String { format!("{}{}", base_url.trim_end_matches('/'), path) }
+
+pub async fn chat_complete(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: &str,
+    req: ChatRequest,
+) -> Result<ChatResponse> {
+    let body = MessagesRequest::from_chat(req);
+
+    let builder = ctx
+        .http
+        .post(build_endpoint(base_url, MESSAGES_PATH))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    #[derive(Debug, Deserialize)]
+    struct ContentBlock { #[serde(rename = "type")] kind: String, text: Option<String> }
+    #[derive(Debug, Deserialize)]
+    struct AnthropicUsage { input_tokens: u64, output_tokens: u64 }
+    #[derive(Debug, Deserialize)]
+    struct MessagesResp { content: Vec<ContentBlock>, model: Option<String>, usage: Option<AnthropicUsage>, stop_reason: Option<String> }
+
+    let body: MessagesResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let usage = body.usage.map(|u| Usage::new(u.input_tokens, u.output_tokens));
+    let content: String = body
+        .content
+        .into_iter()
+        .filter(|b| b.kind == "text")
+        .filter_map(|b| b.text)
+        .collect();
+    if content.is_empty() { return Err(ProviderError::malformed(PROVIDER, "response has no text content").into()); }
+    Ok(ChatResponse { content, usage, model: body.model, finish_reason: body.stop_reason, ..Default::default() })
+}
+
+pub async fn chat_complete_stream(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: &str,
+    req: ChatRequest,
+    on_chunk: &mut OnChunk<'_>,
+) -> Result<ChatResponse> {
+    let mut body = MessagesRequest::from_chat(req);
+    body.stream = Some(true);
+
+    let builder = ctx
+        .http
+        .post(build_endpoint(base_url, MESSAGES_PATH))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    let mut events = SseStream::new(resp.bytes_stream());
+    let mut final_text = String::new();
+    let mut served_model = None;
+    let mut stop_reason = None;
+    let (mut input_tokens, mut output_tokens) = (None, None);
+
+    while let Some(event) = events.next_event().await {
+        let event = event.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        if event.event.as_deref() == Some("error") { return Err(ProviderError::from_body(PROVIDER, &event.data).into()); }
+        let Ok(value) = serde_json::from_str::<Value>(&event.data) else { continue };
+        // Every event repeats its name in the payload `type`
+        match value.get("type").and_then(|v| v.as_str()) {
+            Some("message_start") => {
+                let message = value.get("message");
+                served_model = message.and_then(|m| m.get("model")).and_then(|v| v.as_str()).map(|s| s.to_string());
+                input_tokens = message.and_then(|m| m.get("usage")).and_then(|u| u.get("input_tokens")).and_then(|v| v.as_u64());
+            }
+            // Carries the stop reason and the cumulative output token count
+            Some("message_delta") => {
+                if let Some(reason) = value.get("delta").and_then(|d| d.get("stop_reason")).and_then(|v| v.as_str()) {
+                    stop_reason = Some(reason.to_string());
+                }
+                if let Some(n) = value.get("usage").and_then(|u| u.get("output_tokens")).and_then(|v| v.as_u64()) {
+                    output_tokens = Some(n);
+                }
+            }
+            Some("content_block_delta") => {
+                if let Some(s) = value.get("delta").and_then(|d| d.get("text")).and_then(|v| v.as_str()) {
+                    if !s.is_empty() { on_chunk(s); final_text.push_str(s); }
+                }
+            }
+            Some("message_stop") => break,
+            Some("error") => return Err(ProviderError::from_body(PROVIDER, &event.data).into()),
+            // content_block_start/stop, ping
+            _ => {}
+        }
+    }
+
+    let usage = match (input_tokens, output_tokens) {
+        (None, None) => None,
+        (i, o) => Some(Usage::new(i.unwrap_or(0), o.unwrap_or(0))),
+    };
+    Ok(ChatResponse { content: final_text, usage, model: served_model, finish_reason: stop_reason, ..Default::default() })
+}
+
+pub async fn list_models(ctx: &ProviderContext<'_>, base_url: &str, api_key: &str) -> Result<Vec<ModelInfo>> {
+    #[derive(Deserialize)]
+    struct AnthropicModel { id: String, display_name: Option<String> }
+    #[derive(Deserialize)]
+    struct ModelsResp {
+        data: Vec<AnthropicModel>,
+        #[serde(default)]
+        has_more: bool,
+        last_id: Option<String>,
+    }
+
+    // The list is paged; follow `last_id` until the API says there is no more.
+    let mut models = Vec::new();
+    let mut after_id: Option<String> = None;
+    loop {
+        let mut query = vec![("limit", MODELS_PAGE_SIZE.to_string())];
+        if let Some(id) = &after_id { query.push(("after_id", id.clone())); }
+        let builder = ctx
+            .http
+            .get(build_endpoint(base_url, MODELS_PATH))
+            .query(&query)
+            .headers(build_headers(api_key)?);
+        let resp = retry::send(ctx, builder)
+            .await
+            .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        let resp = check_status(PROVIDER, resp).await?;
+        let body: ModelsResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        models.extend(body.data.into_iter().map(|m| ModelInfo { id: m.id, name: m.display_name, ..Default::default() }));
+        match body.last_id {
+            Some(id) if body.has_more => after_id = Some(id),
+            _ => break,
+        }
+    }
+    Ok(models)
+}
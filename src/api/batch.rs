@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::api::models::{ChatMessage, SamplingParams, Usage};
+use crate::errors::ErrorClass;
+
+/// One line of a `spark batch` input file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// Unique per file; numbers are accepted and kept as strings
+    #[serde(deserialize_with = "id_string")]
+    pub id: String,
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Overrides `--model` / the configured model for this line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Layered over the `[sampling]` defaults and command-line flags
+    #[serde(default)]
+    pub params: SamplingParams,
+}
+
+impl BatchItem {
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+        if let Some(system) = self.system.as_deref().filter(|s| !s.trim().is_empty()) {
+            messages.push(ChatMessage { role: "system".into(), content: system.to_string() });
+        }
+        messages.push(ChatMessage { role: "user".into(), content: self.prompt.clone() });
+        messages
+    }
+}
+
+/// One line of a `spark batch` output file. Exactly one of `content` and `error` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set for provider errors, e.g. `rate_limited`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<ErrorClass>,
+}
+
+fn id_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Ok(s),
+        serde_json::Value::Number(n) => Ok(n.to_string()),
+        other => Err(serde::de::Error::custom(format!("id must be a string or number, got {}", other))),
+    }
+}
+
+/// Parse an input file. Blank lines are skipped; a malformed line or a repeated id is an error.
+pub fn read_items(path: &Path) -> Result<Vec<BatchItem>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read batch input at {}", path.display()))?;
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        let item: BatchItem = serde_json::from_str(line).with_context(|| format!("Invalid batch input line {} in {}", idx + 1, path.display()))?;
+        if !seen.insert(item.id.clone()) {
+            return Err(anyhow!("Duplicate id '{}' on line {} of {}", item.id, idx + 1, path.display()));
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Results already in an output file, oldest first. A missing file has none; a torn
+/// last line (from an interrupted run) is ignored.
+pub fn read_results(path: &Path) -> Result<Vec<BatchResult>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read batch output at {}", path.display())),
+    };
+    let mut results = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        match serde_json::from_str(line) {
+            Ok(r) => results.push(r),
+            Err(e) => tracing::warn!("ignoring unreadable batch output line {} in {}: {}", idx + 1, path.display(), e),
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,175 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::api::catalog::cache_dir;
+use crate::api::models::{ChatMessage, ChatRequest, ChatResponse, SamplingParams};
+
+const RESPONSES_DIR: &str = "responses";
+
+/// Response cache settings (`[cache]` in config). Off unless enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Entries older than this are ignored and removed; 0 keeps them forever
+    pub ttl_secs: u64,
+    /// Least recently used entries are evicted above this total size; 0 disables the limit
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self { enabled: false, ttl_secs: 7 * 24 * 60 * 60, max_size_mb: 100 }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    created_ms: i64,
+    provider: String,
+    response: ChatResponse,
+}
+
+/// What identifies a request; `stream` is left out so streamed and plain runs share entries.
+#[derive(Serialize)]
+struct KeyMaterial<'a> {
+    provider: &'a str,
+    base_url: Option<&'a str>,
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    params: &'a SamplingParams,
+}
+
+/// Hex SHA-256 of the provider, endpoint, model, messages and sampling params.
+pub fn cache_key(provider: &str, base_url: Option<&str>, req: &ChatRequest) -> String {
+    let material = KeyMaterial { provider, base_url, model: &req.model, messages: &req.messages, params: &req.params };
+    let bytes = serde_json::to_vec(&material).expect("cache key material serializes");
+    Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Totals for `spark cache stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    /// Entries past the TTL (or unreadable); they are removed on their next lookup
+    pub expired: usize,
+    pub oldest_ms: Option<i64>,
+    pub newest_ms: Option<i64>,
+}
+
+/// Content-addressed store of completed responses, one JSON file per key.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    settings: CacheSettings,
+}
+
+impl ResponseCache {
+    /// `~/.spark_cli/cache/responses`
+    pub fn new(settings: CacheSettings) -> Self {
+        Self::with_dir(cache_dir().join(RESPONSES_DIR), settings)
+    }
+
+    pub fn with_dir(dir: PathBuf, settings: CacheSettings) -> Self {
+        Self { dir, settings }
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    fn entry_path(&self, key: &str) -> PathBuf { self.dir.join(format!("{}.json", key)) }
+
+    fn is_expired(&self, created_ms: i64, now_ms: i64) -> bool {
+        self.settings.ttl_secs > 0 && now_ms - created_ms > self.settings.ttl_secs as i64 * 1000
+    }
+
+    /// A fresh cached response. Hits refresh the entry's last-used time for eviction.
+    pub fn get(&self, key: &str) -> Option<ChatResponse> {
+        let path = self.entry_path(key);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+        if self.is_expired(entry.created_ms, chrono::Utc::now().timestamp_millis()) {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        if let Ok(file) = File::options().write(true).open(&path) { let _ = file.set_modified(SystemTime::now()); }
+        Some(ChatResponse { cached: true, ..entry.response })
+    }
+
+    /// Store a finished response, then evict down to the size limit.
+    /// Interrupted and empty replies are not cached.
+    pub fn put(&self, key: &str, provider: &str, resp: &ChatResponse) -> Result<()> {
+        if resp.interrupted || resp.content.is_empty() { return Ok(()); }
+        fs::create_dir_all(&self.dir).with_context(|| format!("Failed to create cache directory at {}", self.dir.display()))?;
+        let entry = CacheEntry {
+            created_ms: chrono::Utc::now().timestamp_millis(),
+            provider: provider.to_string(),
+            response: ChatResponse { cached: false, ..resp.clone() },
+        };
+        // Write then rename so a concurrent reader never sees half an entry
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&entry)?).with_context(|| format!("Failed to write cache entry at {}", tmp.display()))?;
+        fs::rename(&tmp, &path)?;
+        self.evict()
+    }
+
+    /// Remove the least recently used entries until the total is under `max_size_mb`.
+    /// Only file metadata is read; expired entries go when they are next looked up.
+    pub fn evict(&self) -> Result<()> {
+        if self.settings.max_size_mb == 0 { return Ok(()); }
+        let limit = self.settings.max_size_mb * 1024 * 1024;
+        let mut files = self.files()?;
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(_, _, used)| *used);
+        for (path, size, _) in files {
+            if total <= limit { break; }
+            fs::remove_file(&path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut stats = CacheStats::default();
+        for (path, size, _) in self.files()? {
+            stats.entries += 1;
+            stats.bytes += size;
+            let entry = fs::read(&path).ok().and_then(|b| serde_json::from_slice::<CacheEntry>(&b).ok());
+            let Some(entry) = entry else { stats.expired += 1; continue };
+            if self.is_expired(entry.created_ms, now) { stats.expired += 1; }
+            stats.oldest_ms = Some(stats.oldest_ms.map_or(entry.created_ms, |o| o.min(entry.created_ms)));
+            stats.newest_ms = Some(stats.newest_ms.map_or(entry.created_ms, |n| n.max(entry.created_ms)));
+        }
+        Ok(stats)
+    }
+
+    /// Delete every entry; returns how many were removed.
+    pub fn clear(&self) -> Result<usize> {
+        let files = self.files()?;
+        for (path, ..) in &files { fs::remove_file(path)?; }
+        Ok(files.len())
+    }
+
+    /// Every entry file with its size and last-used time.
+    fn files(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read cache directory at {}", self.dir.display())),
+        };
+        let mut out = Vec::new();
+        for item in dir {
+            let path = item?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") { continue; }
+            let meta = fs::metadata(&path)?;
+            out.push((path, meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+        }
+        Ok(out)
+    }
+}
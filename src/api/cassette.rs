@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::StreamExt;
+use reqwest::{Client, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::api::providers::ProviderRegistry;
+use crate::config::settings::Settings;
+
+/// Cassette file to record to or replay from
+pub const CASSETTE_ENV: &str = "SPARK_CASSETTE";
+/// `record` or `replay` (default)
+pub const CASSETTE_MODE_ENV: &str = "SPARK_CASSETTE_MODE";
+
+const REDACTED: &str = "REDACTED";
+/// Request headers that carry credentials
+const SECRET_HEADERS: &[&str] = &["authorization", "proxy-authorization", "x-api-key", "x-goog-api-key", "api-key"];
+/// Connection-level headers that are not part of a recording
+const HOP_HEADERS: &[&str] = &["host", "connection", "content-length", "transfer-encoding", "keep-alive", "date"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Forward to the real endpoint and save every exchange
+    Record,
+    /// Answer from the cassette file without touching the network
+    Replay,
+}
+
+/// Recorded HTTP exchanges, in the order they happened.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read cassette at {}", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("Invalid cassette at {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) { std::fs::create_dir_all(parent)?; }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write cassette at {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// A request as the provider sent it, with credentials redacted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query, e.g. `/api/v1/chat/completions`
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+    /// JSON bodies are stored as JSON, anything else as a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
+/// Raw response bytes, kept in the chunks they arrived in so streams replay the same way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub chunks: Vec<String>,
+}
+
+/// A request read off the loopback socket.
+struct Incoming {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Incoming {
+    fn recorded(&self) -> RecordedRequest {
+        let headers = self
+            .headers
+            .iter()
+            .filter(|(k, _)| !HOP_HEADERS.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), if SECRET_HEADERS.contains(&k.as_str()) { REDACTED.to_string() } else { v.clone() }))
+            .collect();
+        let body = (!self.body.is_empty()).then(|| {
+            serde_json::from_slice(&self.body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&self.body).into_owned()))
+        });
+        RecordedRequest { method: self.method.clone(), path: redact_query(&self.target), headers, body }
+    }
+}
+
+/// Blank out `key=` query parameters (Gemini accepts the API key there).
+fn redact_query(target: &str) -> String {
+    let Some((path, query)) = target.split_once('?') else { return target.to_string() };
+    let params: Vec<String> = query
+        .split('&')
+        .map(|p| match p.split_once('=') {
+            Some((k, _)) if k == "key" || k == "api_key" => format!("{}={}", k, REDACTED),
+            _ => p.to_string(),
+        })
+        .collect();
+    format!("{}?{}", path, params.join("&"))
+}
+
+enum Backend {
+    Replay { remaining: Mutex<VecDeque<Interaction>> },
+    Record { upstream: String, http: Client, path: PathBuf, cassette: Mutex<Cassette> },
+}
+
+struct Shared {
+    backend: Backend,
+    received: Mutex<Vec<RecordedRequest>>,
+}
+
+/// Loopback HTTP server that providers are pointed at via `base_url`.
+///
+/// Replay serves recorded responses in order, matching on method and path.
+/// Record forwards to `upstream` with the shared client and appends each
+/// exchange to the cassette file as it completes. The server stops on drop.
+pub struct CassetteServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl CassetteServer {
+    pub async fn replay(path: &Path) -> Result<Self> {
+        let cassette = Cassette::load(path)?;
+        Self::start(Backend::Replay { remaining: Mutex::new(cassette.interactions.into()) }).await
+    }
+
+    /// Record traffic for `upstream`, a URL whose scheme and host are kept (its path is ignored).
+    pub async fn record(path: &Path, upstream: &str, http: Client) -> Result<Self> {
+        let upstream = origin(upstream).ok_or_else(|| anyhow!("cassette: invalid upstream URL '{}'", upstream))?.to_string();
+        let cassette = Mutex::new(Cassette::default());
+        Self::start(Backend::Record { upstream, http, path: path.to_path_buf(), cassette }).await
+    }
+
+    async fn start(backend: Backend) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.context("cassette: failed to bind loopback port")?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared { backend, received: Mutex::new(Vec::new()) });
+        let state = shared.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(&state, socket).await { tracing::warn!("cassette: {:#}", e); }
+                });
+            }
+        });
+        Ok(Self { addr, shared, task })
+    }
+
+    /// `http://127.0.0.1:<port>`
+    pub fn url(&self) -> String { format!("http://{}", self.addr) }
+
+    /// `base_url` with its scheme and host replaced by this server, keeping the path.
+    pub fn rebase(&self, base_url: &str) -> String {
+        let path = origin(base_url).map(|o| &base_url[o.len()..]).unwrap_or("");
+        format!("{}{}", self.url(), path)
+    }
+
+    /// Requests received so far, redacted as they would be recorded.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.shared.received.lock().unwrap().clone()
+    }
+
+    /// Recorded interactions not yet served (always empty when recording).
+    pub fn unused(&self) -> usize {
+        match &self.shared.backend {
+            Backend::Replay { remaining } => remaining.lock().unwrap().len(),
+            Backend::Record { .. } => 0,
+        }
+    }
+}
+
+impl Drop for CassetteServer {
+    fn drop(&mut self) { self.task.abort(); }
+}
+
+/// Start a cassette server when `SPARK_CASSETTE` is set and point the selected
+/// provider's `base_url` at it. The server runs until the returned value is dropped.
+pub async fn from_env(settings: &mut Settings, provider: Option<&str>, http: &Client) -> Result<Option<CassetteServer>> {
+    let Some(path) = std::env::var_os(CASSETTE_ENV).filter(|p| !p.is_empty()).map(PathBuf::from) else { return Ok(None) };
+    let mode = match std::env::var(CASSETTE_MODE_ENV).ok().as_deref().map(str::trim) {
+        None | Some("") | Some("replay") => CassetteMode::Replay,
+        Some("record") => CassetteMode::Record,
+        Some(other) => return Err(anyhow!("{} must be 'record' or 'replay', got '{}'", CASSETTE_MODE_ENV, other)),
+    };
+    let name = provider.unwrap_or(&settings.provider).to_string();
+    let selected = ProviderRegistry::for_settings(settings).get(&name)?;
+    let configured = settings.is_configured_provider(&name);
+    let base_url = settings
+        .base_url
+        .clone()
+        .filter(|_| configured)
+        .or_else(|| selected.default_base_url().map(str::to_string))
+        .ok_or_else(|| anyhow!("cassette: provider {} has no base_url to record or replay", name))?;
+    let server = match mode {
+        CassetteMode::Record => CassetteServer::record(&path, &base_url, http.clone()).await?,
+        CassetteMode::Replay => CassetteServer::replay(&path).await?,
+    };
+    tracing::debug!("cassette {:?} ({:?}) serving {} at {}", path, mode, base_url, server.url());
+    // The rebased URL is read as the configured provider's; when `--provider` picked
+    // another one, it takes that place without the top-level key and model
+    if !configured {
+        settings.provider = name;
+        settings.api_key = None;
+        settings.model = None;
+    }
+    settings.base_url = Some(server.rebase(&base_url));
+    Ok(Some(server))
+}
+
+/// `scheme://host[:port]` prefix of a URL.
+fn origin(url: &str) -> Option<&str> {
+    let after_scheme = url.find("://")? + 3;
+    let end = url[after_scheme..].find('/').map(|i| after_scheme + i).unwrap_or(url.len());
+    Some(&url[..end])
+}
+
+async fn serve(state: &Shared, mut socket: TcpStream) -> Result<()> {
+    let Some(incoming) = read_request(&mut socket).await? else { return Ok(()) };
+    let request = incoming.recorded();
+    state.received.lock().unwrap().push(request.clone());
+
+    match &state.backend {
+        Backend::Replay { remaining } => {
+            let hit = {
+                let mut remaining = remaining.lock().unwrap();
+                let pos = remaining.iter().position(|i| i.request.method == request.method && i.request.path == request.path);
+                pos.and_then(|p| remaining.remove(p))
+            };
+            match hit {
+                Some(interaction) => {
+                    let resp = interaction.response;
+                    write_head(&mut socket, resp.status, &resp.headers).await?;
+                    for chunk in &resp.chunks {
+                        socket.write_all(chunk.as_bytes()).await?;
+                        socket.flush().await?;
+                    }
+                }
+                None => {
+                    // 501 is not retried, so a missing recording fails fast
+                    let message = format!("cassette: no recorded interaction left for {} {}", request.method, request.path);
+                    write_error(&mut socket, 501, &message).await?;
+                }
+            }
+        }
+        Backend::Record { upstream, http, path, cassette } => {
+            let method = Method::from_bytes(incoming.method.as_bytes())?;
+            let mut builder = http.request(method, format!("{}{}", upstream, incoming.target)).body(incoming.body);
+            for (k, v) in incoming.headers.iter().filter(|(k, _)| !HOP_HEADERS.contains(&k.as_str())) {
+                builder = builder.header(k, v);
+            }
+            let resp = match builder.send().await {
+                Ok(r) => r,
+                Err(e) => {
+                    write_error(&mut socket, 502, &format!("cassette: upstream request failed: {}", e)).await?;
+                    return Ok(());
+                }
+            };
+            let status = resp.status().as_u16();
+            let headers: BTreeMap<String, String> = resp
+                .headers()
+                .iter()
+                .filter(|(k, _)| !HOP_HEADERS.contains(&k.as_str()))
+                .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+                .collect();
+            write_head(&mut socket, status, &headers).await?;
+
+            // Each chunk is forwarded once the next one arrives, and the last only after
+            // saving, so the client cannot finish (and the process exit) before the write
+            let mut chunks = Vec::new();
+            let mut pending = Vec::new();
+            let mut held = None;
+            let mut body = resp.bytes_stream();
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                if let Some(prev) = held.replace(chunk.clone()) {
+                    socket.write_all(&prev).await?;
+                    socket.flush().await?;
+                }
+                pending.extend_from_slice(&chunk);
+                let text = take_utf8(&mut pending);
+                if !text.is_empty() { chunks.push(text); }
+            }
+            if !pending.is_empty() { chunks.push(String::from_utf8_lossy(&pending).into_owned()); }
+
+            {
+                let mut cassette = cassette.lock().unwrap();
+                cassette.interactions.push(Interaction { request, response: RecordedResponse { status, headers, chunks } });
+                cassette.save(path)?;
+            }
+            if let Some(last) = held { socket.write_all(&last).await?; }
+        }
+    }
+    socket.shutdown().await.ok();
+    Ok(())
+}
+
+/// Drain the valid UTF-8 prefix of `pending`, leaving a character split across chunks for the next one.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Not UTF-8 at all; store it lossily rather than stall
+        Err(_) => return String::from_utf8_lossy(&std::mem::take(pending)).into_owned(),
+    };
+    let rest = pending.split_off(valid);
+    String::from_utf8(std::mem::replace(pending, rest)).unwrap_or_default()
+}
+
+/// Read one HTTP/1.1 request. Only `Content-Length` bodies are supported, which is what reqwest sends for JSON.
+async fn read_request(socket: &mut TcpStream) -> Result<Option<Incoming>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") { break pos + 4; }
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 { return Ok(None); }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().ok_or_else(|| anyhow!("empty request line"))?.to_string();
+    let target = request_line.next().ok_or_else(|| anyhow!("request line has no target"))?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+    let length = headers.iter().find(|(k, _)| k == "content-length").and_then(|(_, v)| v.parse::<usize>().ok()).unwrap_or(0);
+    while buf.len() < head_end + length {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 { break; }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = buf[head_end..(head_end + length).min(buf.len())].to_vec();
+    Ok(Some(Incoming { method, target, headers, body }))
+}
+
+/// Status line and headers. The body is delimited by closing the connection,
+/// so chunks can be written as they arrive.
+async fn write_head(socket: &mut TcpStream, status: u16, headers: &BTreeMap<String, String>) -> Result<()> {
+    let reason = StatusCode::from_u16(status).ok().and_then(|s| s.canonical_reason()).unwrap_or("");
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (k, v) in headers { head.push_str(&format!("{}: {}\r\n", k, v)); }
+    head.push_str("connection: close\r\n\r\n");
+    socket.write_all(head.as_bytes()).await?;
+    Ok(())
+}
+
+async fn write_error(socket: &mut TcpStream, status: u16, message: &str) -> Result<()> {
+    let headers = BTreeMap::from([("content-type".to_string(), "application/json".to_string())]);
+    write_head(socket, status, &headers).await?;
+    let body = serde_json::json!({ "error": { "message": message } });
+    socket.write_all(body.to_string().as_bytes()).await?;
+    socket.shutdown().await.ok();
+    Ok(())
+}
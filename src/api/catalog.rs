@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::ModelInfo;
+use crate::api::pricing::ModelPrice;
+use crate::api::providers::{ChatProvider, ProviderContext};
+
+const APP_DIR_NAME: &str = ".spark_cli";
+const CACHE_DIR: &str = "cache";
+/// Model lists change rarely; refetch at most once a day
+const CATALOG_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedCatalog {
+    fetched_ms: i64,
+    models: Vec<ModelInfo>,
+}
+
+/// `~/.spark_cli/cache`
+pub fn cache_dir() -> PathBuf {
+    home_dir().unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR_NAME).join(CACHE_DIR)
+}
+
+/// One file per provider, and per endpoint when `base_url` is set (OpenAI-compatible
+/// aliases share a provider name but not a model list).
+fn catalog_path(provider: &str, base_url: Option<&str>) -> PathBuf {
+    let name = match base_url {
+        Some(url) => {
+            let host: String = url
+                .split("://")
+                .last()
+                .unwrap_or(url)
+                .trim_end_matches('/')
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+                .collect();
+            format!("models-{}@{}.json", provider, host)
+        }
+        None => format!("models-{}.json", provider),
+    };
+    cache_dir().join(name)
+}
+
+fn read_cache(path: &Path) -> Option<CachedCatalog> {
+    std::fs::read(path).ok().and_then(|bytes| serde_json::from_slice::<CachedCatalog>(&bytes).ok())
+}
+
+/// Cached model list regardless of age, without touching the network.
+pub fn load_cached(provider: &str, base_url: Option<&str>) -> Option<Vec<ModelInfo>> {
+    read_cache(&catalog_path(provider, base_url)).map(|c| c.models)
+}
+
+/// Provider model list, served from the on-disk cache while it is fresh.
+/// `refresh` forces a fetch. A stale cache is still used if the fetch fails.
+pub async fn cached_models(provider: &dyn ChatProvider, ctx: &ProviderContext<'_>, refresh: bool) -> Result<Vec<ModelInfo>> {
+    let path = catalog_path(provider.name(), ctx.base_url);
+    let now = chrono::Utc::now().timestamp_millis();
+    let cached = read_cache(&path);
+    if let Some(c) = &cached {
+        if !refresh && now - c.fetched_ms < CATALOG_TTL_MS {
+            return Ok(c.models.clone());
+        }
+    }
+    match provider.list_models(ctx).await {
+        Ok(models) => {
+            std::fs::create_dir_all(cache_dir())?;
+            let entry = CachedCatalog { fetched_ms: now, models };
+            std::fs::write(&path, serde_json::to_vec(&entry)?)
+                .with_context(|| format!("Failed to write model cache at {}", path.display()))?;
+            Ok(entry.models)
+        }
+        Err(e) => match cached {
+            Some(c) => {
+                tracing::warn!("model list refresh failed, using stale cache: {}", e);
+                Ok(c.models)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Filters for `spark models`.
+#[derive(Debug, Clone, Default)]
+pub struct ModelFilter {
+    /// Case-insensitive substring of the id or display name
+    pub query: Option<String>,
+    pub min_context: Option<u64>,
+    /// Upper bound on the prompt price, USD per million tokens
+    pub max_price: Option<f64>,
+}
+
+impl ModelFilter {
+    /// Models with an unknown context length or price never pass the matching bound.
+    pub fn matches(&self, model: &ModelInfo) -> bool {
+        if let Some(q) = &self.query {
+            let q = q.to_lowercase();
+            let in_name = model.name.as_deref().is_some_and(|n| n.to_lowercase().contains(&q));
+            if !model.id.to_lowercase().contains(&q) && !in_name { return false; }
+        }
+        if let Some(min) = self.min_context {
+            if model.context_length.is_none_or(|c| c < min) { return false; }
+        }
+        if let Some(max) = self.max_price {
+            let price = model.pricing.as_ref().and_then(ModelPrice::from_pricing);
+            if price.is_none_or(|p| p.prompt > max) { return false; }
+        }
+        true
+    }
+}
+
+/// Whether `id` names a listed model. Ollama lists tags, so `llama3.2` matches `llama3.2:latest`.
+pub fn contains_model(models: &[ModelInfo], id: &str) -> bool {
+    models.iter().any(|m| m.id == id || m.id.strip_suffix(":latest") == Some(id))
+}
+
+/// Closest model ids to `id`, best first: substring hits, then small edit distances
+/// (compared without the `vendor/` prefix so `gpt4o` finds `openai/gpt-4o`).
+pub fn suggest(models: &[ModelInfo], id: &str, limit: usize) -> Vec<String> {
+    let needle = id.to_lowercase();
+    let short = |s: &str| s.rsplit('/').next().unwrap_or(s).to_string();
+    let max_distance = (short(&needle).chars().count() / 3).max(2);
+    let mut scored: Vec<(usize, &str)> = models
+        .iter()
+        .filter_map(|m| {
+            let candidate = m.id.to_lowercase();
+            if candidate.contains(&needle) || needle.contains(&candidate) { return Some((0, m.id.as_str())); }
+            let d = edit_distance(&short(&needle), &short(&candidate)).min(edit_distance(&needle, &candidate));
+            (d <= max_distance).then_some((d, m.id.as_str()))
+        })
+        .collect();
+    scored.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.len().cmp(&b.1.len())).then(a.1.cmp(b.1)));
+    scored.into_iter().take(limit).map(|(_, id)| id.to_string()).collect()
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
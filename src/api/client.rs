@@ -1,16 +1,83 @@
-use anyhow::Result;
-use reqwest::Client;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
-#[derive(Clone)]
-pub struct HttpClient {
-    pub client: Client,
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_USER_AGENT: &str = concat!("spark_cli/", env!("CARGO_PKG_VERSION"));
+
+/// Transport settings for the shared HTTP client (`[http]` in config).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Time allowed to connect, including the TLS handshake; 0 disables
+    pub connect_timeout_secs: u64,
+    /// Longest wait for the next bytes of a response; 0 disables. There is no
+    /// total timeout, so long streamed replies are not cut off while they flow.
+    pub read_timeout_secs: u64,
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy for every request.
+    /// When unset, `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` are used; `NO_PROXY` always applies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// PEM file with extra root certificates, e.g. for a TLS-inspecting corporate proxy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+    /// Defaults to `spark_cli/<version>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Extra request headers per provider name (`[http.headers.openrouter]`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, BTreeMap<String, String>>,
 }
 
-impl HttpClient {
-    pub fn new() -> Result<Self> {
-        let client = Client::builder()
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 120,
+            proxy: None,
+            ca_cert: None,
+            user_agent: None,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl HttpSettings {
+    /// The client shared by every provider call in a run.
+    pub fn build_client(&self) -> Result<Client> {
+        let mut builder = Client::builder()
             .pool_max_idle_per_host(8)
-            .build()?;
-        Ok(Self { client })
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+        if self.connect_timeout_secs > 0 { builder = builder.connect_timeout(Duration::from_secs(self.connect_timeout_secs)); }
+        if self.read_timeout_secs > 0 { builder = builder.read_timeout(Duration::from_secs(self.read_timeout_secs)); }
+        if let Some(url) = self.proxy.as_deref().filter(|s| !s.trim().is_empty()) {
+            let proxy = Proxy::all(url.trim()).with_context(|| format!("Invalid http.proxy '{}'", url))?;
+            builder = builder.proxy(proxy.no_proxy(NoProxy::from_env()));
+        }
+        if let Some(path) = self.ca_cert.as_deref() {
+            let pem = std::fs::read(path).with_context(|| format!("Failed to read http.ca_cert at {}", path))?;
+            let certs = Certificate::from_pem_bundle(&pem).with_context(|| format!("Invalid PEM in http.ca_cert at {}", path))?;
+            if certs.is_empty() { return Err(anyhow!("No certificates found in http.ca_cert at {}", path)); }
+            for cert in certs { builder = builder.add_root_certificate(cert); }
+        }
+        builder.build().context("Failed to build HTTP client")
+    }
+
+    /// Extra headers for a provider, looked up under each of `names` (e.g. the
+    /// registered implementation name, then the alias the user selected); later names win.
+    pub fn headers_for(&self, names: &[&str]) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for name in names {
+            let Some(table) = self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v) else { continue };
+            for (key, value) in table {
+                let header = HeaderName::from_bytes(key.as_bytes()).with_context(|| format!("Invalid header name '{}' in [http.headers.{}]", key, name))?;
+                let value = HeaderValue::from_str(value).with_context(|| format!("Invalid value for header '{}' in [http.headers.{}]", key, name))?;
+                headers.insert(header, value);
+            }
+        }
+        Ok(headers)
     }
 }
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ErrorClass;
+
+/// Errors that move on to the next backend when a target sets no `on` list:
+/// the provider is down, overloaded or does not have the model.
+pub const DEFAULT_TRIGGERS: &[ErrorClass] = &[
+    ErrorClass::RateLimited,
+    ErrorClass::Server,
+    ErrorClass::Timeout,
+    ErrorClass::Network,
+    ErrorClass::ModelNotFound,
+];
+
+/// One backend in the `[[fallback]]` chain, tried in order after the primary provider fails.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallbackTarget {
+    /// Registered provider name, e.g. `deepseek` or `anthropic`
+    pub provider: String,
+    /// Defaults to the provider's own default model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Required for OpenAI-compatible providers; the top-level `base_url` is only
+    /// reused when this is the same provider as the primary one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Defaults to the provider's API key env var (or the top-level `api_key` for the same provider)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Error classes routed to this target; empty means [`DEFAULT_TRIGGERS`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on: Vec<ErrorClass>,
+}
+
+impl FallbackTarget {
+    pub fn handles(&self, class: ErrorClass) -> bool {
+        if self.on.is_empty() { DEFAULT_TRIGGERS.contains(&class) } else { self.on.contains(&class) }
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::models::{ChatRequest, ChatResponse, ModelInfo, SamplingParams, Usage};
+use crate::api::retry;
+use crate::api::sse::SseStream;
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::{check_status, ProviderError};
+
+/// Provider name reported in errors
+const PROVIDER: &str = "google";
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-2.0-flash";
+/// Largest page the `models` list accepts
+const MODELS_PAGE_SIZE: u32 = 1000;
+
+/// Google Gemini `generateContent` API (`provider = "google"` or `"gemini"`).
+pub struct Gemini;
+
+#[async_trait]
+impl ChatProvider for Gemini {
+    fn name(&self) -> &'static str { "google" }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: true, requires_base_url: false, pricing: false }
+    }
+
+    fn api_key_env(&self) -> Option<&'static str> { Some("GEMINI_API_KEY") }
+
+    fn default_base_url(&self) -> Option<&'static str> { Some(DEFAULT_BASE_URL) }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        chat_complete(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?, req).await
+    }
+
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        chat_complete_stream(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?, req, on_chunk).await
+    }
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        list_models(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.require_api_key()?).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Part {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+}
+
+impl GenerateRequest {
+    /// Map chat roles onto Gemini: `assistant` becomes `model`, and `system`
+    /// messages are folded into `systemInstruction`.
+    fn from_chat(req: ChatRequest) -> (String, Self) {
+        let model = if req.model.is_empty() { DEFAULT_MODEL.to_string() } else { req.model };
+        let mut system = Vec::new();
+        let mut contents = Vec::new();
+        for m in req.messages {
+            match m.role.as_str() {
+                "system" => system.push(Part { text: m.content }),
+                "assistant" => contents.push(Content { role: Some("model".into()), parts: vec![Part { text: m.content }] }),
+                _ => contents.push(Content { role: Some("user".into()), parts: vec![Part { text: m.content }] }),
+            }
+        }
+        let system_instruction = if system.is_empty() { None } else { Some(Content { role: None, parts: system }) };
+        let p = req.params;
+        let generation_config = if p == SamplingParams::default() {
+            None
+        } else {
+            Some(GenerationConfig {
+                temperature: p.temperature,
+                top_p: p.top_p,
+                max_output_tokens: p.max_tokens,
+                stop_sequences: p.stop,
+                seed: p.seed,
+                presence_penalty: p.presence_penalty,
+                frequency_penalty: p.frequency_penalty,
+            })
+        };
+        (model, Self { contents, system_instruction, generation_config })
+    }
+}
+
+fn build_headers(api_key: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-goog-api-key", HeaderValue::from_str(api_key)?);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(headers)
+}
+
+/// Model ids may be given with or without the `models/` prefix.
+fn model_endpoint(base_url: &str, model: &str, method: &str) -> String {
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    format!("{}/models/{}:{}", base_url.trim_end_matches('/'), model, method)
+}
+
+/// Render `safetyRatings` as "CATEGORY=PROBABILITY" pairs, skipping negligible ones.
+fn describe_ratings(ratings: Option<&Value>) -> String {
+    let items: Vec<String> = ratings
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter(|r| r.get("probability").and_then(|p| p.as_str()) != Some("NEGLIGIBLE"))
+                .map(|r| {
+                    let category = r.get("category").and_then(|c| c.as_str()).unwrap_or("UNKNOWN");
+                    let category = category.strip_prefix("HARM_CATEGORY_").unwrap_or(category);
+                    let probability = r.get("probability").and_then(|p| p.as_str()).unwrap_or("UNKNOWN");
+                    format!("{}={}", category, probability)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if items.is_empty() { "no ratings reported".to_string() } else { items.join(", ") }
+}
+
+/// Pull the text out of one `GenerateContentResponse`, turning safety blocks into errors.
+fn extract_text(value: &Value) -> Result<String, ProviderError> {
+    if value.get("error").is_some() { return Err(ProviderError::from_body(PROVIDER, &value.to_string())); }
+    if let Some(feedback) = value.get("promptFeedback") {
+        if let Some(reason) = feedback.get("blockReason").and_then(|r| r.as_str()) {
+            return Err(ProviderError::content_filtered(
+                PROVIDER,
+                format!("prompt blocked ({}): {}", reason, describe_ratings(feedback.get("safetyRatings"))),
+            ));
+        }
+    }
+    let Some(candidate) = value.get("candidates").and_then(|c| c.get(0)) else { return Ok(String::new()) };
+    let text: String = candidate
+        .get("content")
+        .and_then(|c| c.get("parts"))
+        .and_then(|p| p.as_array())
+        .map(|parts| parts.iter().filter_map(|p| p.get("text").and_then(|t| t.as_str())).collect())
+        .unwrap_or_default();
+    let finish = candidate.get("finishReason").and_then(|f| f.as_str());
+    if text.is_empty() && matches!(finish, Some("SAFETY" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "RECITATION")) {
+        return Err(ProviderError::content_filtered(
+            PROVIDER,
+            format!("response stopped ({}): {}", finish.unwrap_or_default(), describe_ratings(candidate.get("safetyRatings"))),
+        ));
+    }
+    Ok(text)
+}
+
+/// `usageMetadata` is cumulative, so the last one seen in a stream is the total.
+fn extract_usage(value: &Value) -> Option<Usage> {
+    let meta = value.get("usageMetadata")?;
+    let prompt = meta.get("promptTokenCount").and_then(|v| v.as_u64()).unwrap_or(0);
+    let completion = meta.get("candidatesTokenCount").and_then(|v| v.as_u64()).unwrap_or(0);
+    let mut usage = Usage::new(prompt, completion);
+    if let Some(total) = meta.get("totalTokenCount").and_then(|v| v.as_u64()) { usage.total_tokens = total; }
+    Some(usage)
+}
+
+fn extract_finish_reason(value: &Value) -> Option<String> {
+    let candidate = value.get("candidates").and_then(|c| c.get(0))?;
+    candidate.get("finishReason").and_then(|f| f.as_str()).map(|s| s.to_string())
+}
+
+fn extract_model(value: &Value) -> Option<String> {
+    value.get("modelVersion").and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+pub async fn chat_complete(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: &str,
+    req: ChatRequest,
+) -> Result<ChatResponse> {
+    let (model, body) = GenerateRequest::from_chat(req);
+
+    let builder = ctx
+        .http
+        .post(model_endpoint(base_url, &model, "generateContent"))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    let value: Value = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let content = extract_text(&value)?;
+    if content.is_empty() { return Err(ProviderError::malformed(PROVIDER, "response has no candidates").into()); }
+    Ok(ChatResponse { content, usage: extract_usage(&value), model: extract_model(&value), finish_reason: extract_finish_reason(&value), ..Default::default() })
+}
+
+pub async fn chat_complete_stream(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: &str,
+    req: ChatRequest,
+    on_chunk: &mut OnChunk<'_>,
+) -> Result<ChatResponse> {
+    let (model, body) = GenerateRequest::from_chat(req);
+
+    let builder = ctx
+        .http
+        .post(format!("{}?alt=sse", model_endpoint(base_url, &model, "streamGenerateContent")))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    let is_sse = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("text/event-stream"))
+        .unwrap_or(false);
+    let mut final_text = String::new();
+    let mut usage = None;
+    let mut served_model = None;
+    let mut finish_reason = None;
+
+    if !is_sse {
+        // Proxies may ignore `alt=sse` and return the whole stream as one JSON array
+        let value: Value = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        let items = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+        for item in &items {
+            let s = extract_text(item)?;
+            if !s.is_empty() { on_chunk(&s); final_text.push_str(&s); }
+            usage = extract_usage(item).or(usage);
+            served_model = extract_model(item).or(served_model);
+            finish_reason = extract_finish_reason(item).or(finish_reason);
+        }
+        return Ok(ChatResponse { content: final_text, usage, model: served_model, finish_reason, ..Default::default() });
+    }
+
+    let mut events = SseStream::new(resp.bytes_stream());
+    while let Some(event) = events.next_event().await {
+        let event = event.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        let Ok(value) = serde_json::from_str::<Value>(&event.data) else { continue };
+        let s = extract_text(&value)?;
+        if !s.is_empty() { on_chunk(&s); final_text.push_str(&s); }
+        usage = extract_usage(&value).or(usage);
+        served_model = extract_model(&value).or(served_model);
+        finish_reason = extract_finish_reason(&value).or(finish_reason);
+    }
+
+    Ok(ChatResponse { content: final_text, usage, model: served_model, finish_reason, ..Default::default() })
+}
+
+pub async fn list_models(ctx: &ProviderContext<'_>, base_url: &str, api_key: &str) -> Result<Vec<ModelInfo>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct GeminiModel { name: String, display_name: Option<String>, input_token_limit: Option<u64> }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ModelsResp {
+        #[serde(default)]
+        models: Vec<GeminiModel>,
+        #[serde(default)]
+        next_page_token: String,
+    }
+
+    // The list is paged; follow `nextPageToken` until it comes back empty.
+    let mut models = Vec::new();
+    let mut page_token = String::new();
+    loop {
+        let mut query = vec![("pageSize", MODELS_PAGE_SIZE.to_string())];
+        if !page_token.is_empty() { query.push(("pageToken", page_token.clone())); }
+        let builder = ctx
+            .http
+            .get(format!("{}/models", base_url.trim_end_matches('/')))
+            .query(&query)
+            .headers(build_headers(api_key)?);
+        let resp = retry::send(ctx, builder)
+            .await
+            .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        let resp = check_status(PROVIDER, resp).await?;
+        let body: ModelsResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        models.extend(body.models.into_iter().map(|m| ModelInfo {
+            id: m.name.strip_prefix("models/").unwrap_or(&m.name).to_string(),
+            name: m.display_name,
+            context_length: m.input_token_limit,
+            ..Default::default()
+        }));
+        if body.next_page_token.is_empty() { break; }
+        page_token = body.next_page_token;
+    }
+    Ok(models)
+}
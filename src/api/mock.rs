@@ -0,0 +1,201 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{ChatRequest, ChatResponse, ModelInfo, Usage};
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::ProviderError;
+
+/// Provider name reported in errors
+const PROVIDER: &str = "mock";
+const MODEL: &str = "mock";
+
+const CANNED_CODE: &str = "Here is a minimal example.\n\n```rust\nfn main() {\n    println!(\"Hello from the spark mock provider\");\n}\n```\n\nRun it with `cargo run`.\n";
+
+/// How the mock provider builds its reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MockMode {
+    /// Repeat the last user message
+    Echo,
+    /// A fixed answer containing one fenced Rust code block
+    Code,
+    /// Responses from the `fixture` file
+    Script,
+}
+
+/// Offline provider settings (`[mock]` table in config).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockOptions {
+    /// Defaults to `script` when `fixture` is set, otherwise `echo`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<MockMode>,
+    /// TOML (`[[responses]]`) or JSONL file of scripted responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixture: Option<String>,
+    /// Characters per streamed chunk
+    pub chunk_size: usize,
+    /// Delay before each streamed chunk (and before a non-streamed reply)
+    pub latency_ms: u64,
+    /// Fail every request with this HTTP status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_status: Option<u16>,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self { mode: None, fixture: None, chunk_size: 16, latency_ms: 0, error_status: None }
+    }
+}
+
+impl MockOptions {
+    pub fn is_default(&self) -> bool { *self == Self::default() }
+}
+
+/// One scripted response. The first entry whose conditions all hold is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockResponse {
+    /// Case-insensitive text the last user message must contain
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+    /// Conversation turn this applies to (1 = first user message)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn: Option<usize>,
+    /// The reply; with `status`, streamed requests send it before failing
+    pub content: String,
+    /// Fail with this HTTP status instead of answering
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Error body sent with `status`, e.g. an OpenAI-style `{"error": {...}}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Fixture {
+    #[serde(default)]
+    responses: Vec<MockResponse>,
+}
+
+/// Read a fixture: `.jsonl`/`.json` files hold one response object per line,
+/// anything else is TOML with a `[[responses]]` array.
+pub fn load_fixture(path: &Path) -> Result<Vec<MockResponse>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read mock fixture at {}", path.display()))?;
+    let is_jsonl = matches!(path.extension().and_then(|e| e.to_str()), Some("jsonl" | "json"));
+    if is_jsonl {
+        content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line).with_context(|| format!("Invalid mock fixture line {} in {}", i + 1, path.display()))
+            })
+            .collect()
+    } else {
+        let fixture: Fixture = toml::from_str(&content)
+            .with_context(|| format!("Invalid mock fixture TOML at {}", path.display()))?;
+        Ok(fixture.responses)
+    }
+}
+
+/// Built-in offline provider (`provider = "mock"`) for tests and demos.
+#[derive(Default)]
+pub struct Mock {
+    pub options: MockOptions,
+}
+
+impl Mock {
+    fn mode(&self) -> MockMode {
+        self.options.mode.unwrap_or(if self.options.fixture.is_some() { MockMode::Script } else { MockMode::Echo })
+    }
+
+    /// The reply text for `req` and the simulated failure that follows it, if any.
+    fn reply(&self, req: &ChatRequest) -> Result<(String, Option<ProviderError>)> {
+        if let Some(status) = self.options.error_status { return Ok((String::new(), Some(simulated_error(status, None)))); }
+        let last_user = req.messages.iter().rev().find(|m| m.role == "user").map(|m| m.content.as_str()).unwrap_or("");
+        match self.mode() {
+            MockMode::Echo => Ok((last_user.to_string(), None)),
+            MockMode::Code => Ok((CANNED_CODE.to_string(), None)),
+            MockMode::Script => {
+                let path = self.options.fixture.as_deref().ok_or_else(|| anyhow!("mock: script mode needs `fixture` in [mock]"))?;
+                let turn = req.messages.iter().filter(|m| m.role == "user").count();
+                let needle = last_user.to_lowercase();
+                let responses = load_fixture(Path::new(path))?;
+                let hit = responses
+                    .into_iter()
+                    .find(|r| {
+                        r.turn.is_none_or(|t| t == turn)
+                            && r.matches.as_deref().is_none_or(|m| needle.contains(&m.to_lowercase()))
+                    })
+                    .ok_or_else(|| anyhow!("mock: no scripted response in {} for turn {}: {:?}", path, turn, last_user))?;
+                let error = hit.status.map(|status| simulated_error(status, hit.body.as_deref()));
+                Ok((hit.content, error))
+            }
+        }
+    }
+
+    fn response(req: &ChatRequest, content: String) -> ChatResponse {
+        // Rough 4-characters-per-token estimate so usage reporting has something to show
+        let tokens = |chars: usize| chars.div_ceil(4) as u64;
+        let prompt_chars: usize = req.messages.iter().map(|m| m.content.chars().count()).sum();
+        let usage = Usage::new(tokens(prompt_chars), tokens(content.chars().count()));
+        // Report the requested model like a real API would
+        let model = if req.model.is_empty() { MODEL.to_string() } else { req.model.clone() };
+        ChatResponse { content, usage: Some(usage), model: Some(model), finish_reason: Some("stop".into()), ..Default::default() }
+    }
+
+    async fn pause(&self) {
+        if self.options.latency_ms > 0 { tokio::time::sleep(Duration::from_millis(self.options.latency_ms)).await; }
+    }
+}
+
+fn simulated_error(status: u16, body: Option<&str>) -> ProviderError {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let default_body = format!(r#"{{"error":{{"message":"simulated {} from mock provider","code":{}}}}}"#, status, status.as_u16());
+    ProviderError::from_response(PROVIDER, status, body.unwrap_or(&default_body))
+}
+
+#[async_trait]
+impl ChatProvider for Mock {
+    fn name(&self) -> &'static str { PROVIDER }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: false, requires_base_url: false, pricing: false }
+    }
+
+    async fn complete(&self, _ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        self.pause().await;
+        match self.reply(&req)? {
+            (_, Some(error)) => Err(error.into()),
+            (content, None) => Ok(Self::response(&req, content)),
+        }
+    }
+
+    async fn stream(
+        &self,
+        _ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        let (content, error) = self.reply(&req)?;
+        let chars: Vec<char> = content.chars().collect();
+        for piece in chars.chunks(self.options.chunk_size.max(1)) {
+            self.pause().await;
+            on_chunk(&piece.iter().collect::<String>());
+        }
+        // A scripted failure with content breaks off mid-stream
+        if let Some(error) = error { return Err(error.into()); }
+        Ok(Self::response(&req, content))
+    }
+
+    async fn list_models(&self, _ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        Ok(vec![ModelInfo { id: MODEL.to_string(), name: Some("Offline mock".into()), ..Default::default() }])
+    }
+}
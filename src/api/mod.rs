@@ -3,3 +3,16 @@ pub mod providers;
 pub mod models;
 pub mod openrouter;
 pub mod openai_compat;
+pub mod anthropic;
+pub mod google;
+pub mod ollama;
+pub mod mock;
+pub mod pricing;
+pub mod catalog;
+pub mod retry;
+pub mod sse;
+pub mod cassette;
+pub mod cache;
+pub mod fallback;
+pub mod batch;
+pub mod tokens;
@@ -0,0 +1,269 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::models::{ChatMessage, ChatRequest, ChatResponse, ModelInfo, SamplingParams, Usage};
+use crate::api::retry;
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::{check_status, ProviderError};
+
+/// Provider name reported in errors
+const PROVIDER: &str = "ollama";
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+const CHAT_PATH: &str = "/api/chat";
+const TAGS_PATH: &str = "/api/tags";
+const DEFAULT_MODEL: &str = "llama3.2";
+
+/// Ollama-specific request settings (`[ollama]` table in config).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaOptions {
+    /// How long the model stays loaded after a request, e.g. "5m" or "-1"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    /// Context window size passed as `options.num_ctx`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+/// Local models served by Ollama (`provider = "ollama"`). No API key required.
+#[derive(Default)]
+pub struct Ollama {
+    pub options: OllamaOptions,
+}
+
+#[async_trait]
+impl ChatProvider for Ollama {
+    fn name(&self) -> &'static str { "ollama" }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: false, requires_base_url: false, pricing: false }
+    }
+
+    fn default_base_url(&self) -> Option<&'static str> { Some(DEFAULT_BASE_URL) }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        chat_complete(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.api_key, &self.options, req).await
+    }
+
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        chat_complete_stream(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL), ctx.api_key, &self.options, req, on_chunk).await
+    }
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        list_models(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL)).await
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    /// Ollama streams unless told otherwise, so this is always sent
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<ModelOptions>,
+}
+
+impl OllamaChatRequest {
+    /// Request sampling params take precedence over the `[ollama]` defaults.
+    fn new(req: ChatRequest, opts: &OllamaOptions, stream: bool) -> Self {
+        let model = if req.model.is_empty() { DEFAULT_MODEL.to_string() } else { req.model };
+        let p = req.params;
+        let options = if opts.num_ctx.is_some() || opts.temperature.is_some() || p != SamplingParams::default() {
+            Some(ModelOptions {
+                num_ctx: opts.num_ctx,
+                temperature: p.temperature.or(opts.temperature),
+                top_p: p.top_p,
+                num_predict: p.max_tokens,
+                stop: p.stop,
+                seed: p.seed,
+                presence_penalty: p.presence_penalty,
+                frequency_penalty: p.frequency_penalty,
+            })
+        } else {
+            None
+        };
+        Self { model, messages: req.messages, stream, keep_alive: opts.keep_alive.clone(), options }
+    }
+}
+
+/// Ollama needs no auth, but a key is forwarded when set (e.g. behind a reverse proxy).
+fn build_headers(api_key: Option<&str>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    if let Some(key) = api_key {
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", key))?);
+    }
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Ok(headers)
+}
+
+fn build_endpoint(base_url: &str, path: &str) -> String { format!("{}{}", base_url.trim_end_matches('/'), path) }
+
+/// The final object carries `prompt_eval_count` and `eval_count`.
+fn extract_usage(value: &Value) -> Option<Usage> {
+    let prompt = value.get("prompt_eval_count").and_then(|v| v.as_u64());
+    let completion = value.get("eval_count").and_then(|v| v.as_u64());
+    if prompt.is_none() && completion.is_none() { return None; }
+    Some(Usage::new(prompt.unwrap_or(0), completion.unwrap_or(0)))
+}
+
+fn network_error(base_url: &str, e: reqwest::Error) -> ProviderError {
+    let connect = e.is_connect();
+    let mut err = ProviderError::from_reqwest(PROVIDER, e);
+    if let (true, ProviderError::Network(info)) = (connect, &mut err) {
+        info.message = format!("cannot reach Ollama at {} (is `ollama serve` running?): {}", base_url, info.message);
+    }
+    err
+}
+
+pub async fn chat_complete(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: Option<&str>,
+    opts: &OllamaOptions,
+    req: ChatRequest,
+) -> Result<ChatResponse> {
+    let body = OllamaChatRequest::new(req, opts, false);
+
+    let builder = ctx
+        .http
+        .post(build_endpoint(base_url, CHAT_PATH))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| network_error(base_url, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    let value: Value = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    if value.get("error").is_some() { return Err(ProviderError::from_body(PROVIDER, &value.to_string()).into()); }
+    let content = value
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| ProviderError::malformed(PROVIDER, "response has no message"))?;
+    let model = value.get("model").and_then(|m| m.as_str()).map(|s| s.to_string());
+    let finish_reason = value.get("done_reason").and_then(|r| r.as_str()).map(|s| s.to_string());
+    Ok(ChatResponse { content, usage: extract_usage(&value), model, finish_reason, ..Default::default() })
+}
+
+pub async fn chat_complete_stream(
+    ctx: &ProviderContext<'_>,
+    base_url: &str,
+    api_key: Option<&str>,
+    opts: &OllamaOptions,
+    req: ChatRequest,
+    on_chunk: &mut OnChunk<'_>,
+) -> Result<ChatResponse> {
+    let body = OllamaChatRequest::new(req, opts, true);
+
+    let builder = ctx
+        .http
+        .post(build_endpoint(base_url, CHAT_PATH))
+        .headers(build_headers(api_key)?)
+        .json(&body);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| network_error(base_url, e))?;
+
+    let resp = check_status(PROVIDER, resp).await?;
+
+    let mut stream = resp.bytes_stream();
+    let mut buffer = Vec::new();
+    let mut state = StreamState::default();
+    let mut done = false;
+
+    // Newline-delimited JSON: one object per line, the last one has `"done": true`
+    'outer: while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line = buffer.drain(..=pos).collect::<Vec<u8>>();
+            if state.line(&line, on_chunk)? { done = true; break 'outer; }
+        }
+    }
+    // The final object may arrive without a trailing newline
+    if !done { state.line(&buffer, on_chunk)?; }
+
+    Ok(ChatResponse { content: state.text, usage: state.usage, model: state.model, finish_reason: state.finish_reason, ..Default::default() })
+}
+
+/// What the streamed objects have carried so far.
+#[derive(Default)]
+struct StreamState {
+    text: String,
+    usage: Option<Usage>,
+    model: Option<String>,
+    finish_reason: Option<String>,
+}
+
+impl StreamState {
+    /// Apply one NDJSON line; returns `true` for the final `"done": true` object.
+    fn line(&mut self, bytes: &[u8], on_chunk: &mut OnChunk<'_>) -> Result<bool> {
+        let line = String::from_utf8_lossy(bytes).trim().to_string();
+        if line.is_empty() { return Ok(false); }
+        let value: Value = serde_json::from_str(&line)
+            .map_err(|e| ProviderError::malformed(PROVIDER, format!("invalid JSON line: {}", e)))?;
+        if value.get("error").is_some() { return Err(ProviderError::from_body(PROVIDER, &line).into()); }
+        if let Some(s) = value.get("message").and_then(|m| m.get("content")).and_then(|c| c.as_str()) {
+            if !s.is_empty() { on_chunk(s); self.text.push_str(s); }
+        }
+        if self.model.is_none() { self.model = value.get("model").and_then(|m| m.as_str()).map(|s| s.to_string()); }
+        if !value.get("done").and_then(|d| d.as_bool()).unwrap_or(false) { return Ok(false); }
+        self.usage = extract_usage(&value);
+        self.finish_reason = value.get("done_reason").and_then(|r| r.as_str()).map(|s| s.to_string());
+        Ok(true)
+    }
+}
+
+pub async fn list_models(ctx: &ProviderContext<'_>, base_url: &str) -> Result<Vec<ModelInfo>> {
+    #[derive(Deserialize)]
+    struct Tag { name: String }
+    #[derive(Deserialize)]
+    struct TagsResp { #[serde(default)] models: Vec<Tag> }
+
+    let builder = ctx
+        .http
+        .get(build_endpoint(base_url, TAGS_PATH));
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| network_error(base_url, e))?;
+    let resp = check_status(PROVIDER, resp).await?;
+    let body: TagsResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    Ok(body.models.into_iter().map(|m| ModelInfo { id: m.name, ..Default::default() }).collect())
+}
@@ -1,12 +1,17 @@
-use anyhow::{anyhow, Result};
-use futures_util::StreamExt;
+use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::Deserialize;
 
-use crate::api::models::{ChatMessage, ChatRequest};
+use crate::api::models::{ChatRequest, ChatResponse, ModelInfo, StreamOptions, Usage};
+use crate::api::{retry, sse};
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::{check_status, ProviderError};
 
+/// Provider name reported in errors
+const PROVIDER: &str = "openai-compatible";
 const DEFAULT_PATH: &str = "/chat/completions";
+const MODELS_PATH: &str = "/models";
 
 fn build_endpoint(base_url: &str) -> String { format!("{}{}", base_url.trim_end_matches('/'), DEFAULT_PATH) }
 
@@ -20,111 +25,117 @@ fn build_headers(api_key: &str) -> Result<HeaderMap> {
     Ok(headers)
 }
 
+/// Any OpenAI-compatible `/chat/completions` endpoint (OpenAI, DeepSeek, Qwen, proxies).
+/// Registered as `openai-compatible` and the `openai`/`deepseek`/`qwen` aliases.
+pub struct OpenAiCompat;
+
+#[async_trait]
+impl ChatProvider for OpenAiCompat {
+    fn name(&self) -> &'static str { "openai-compatible" }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: true, requires_base_url: true, pricing: false }
+    }
+
+    fn api_key_env(&self) -> Option<&'static str> { Some("OPENAI_API_KEY") }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        chat_complete(ctx, ctx.require_base_url()?, ctx.require_api_key()?, req).await
+    }
+
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        chat_complete_stream(ctx, ctx.require_base_url()?, ctx.require_api_key()?, req, on_chunk).await
+    }
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        list_models(ctx, ctx.require_base_url()?, ctx.require_api_key()?).await
+    }
+}
+
 pub async fn chat_complete(
-    client: &reqwest::Client,
+    ctx: &ProviderContext<'_>,
     base_url: &str,
     api_key: &str,
-    user_messages: Vec<ChatMessage>,
-    model: Option<&str>,
-) -> Result<String> {
+    mut req: ChatRequest,
+) -> Result<ChatResponse> {
     let endpoint = build_endpoint(base_url);
-
-    let req = ChatRequest {
-        model: model.unwrap_or("").to_string(),
-        messages: user_messages,
-        stream: None,
-    };
+    req.stream = None;
 
     let headers = build_headers(api_key)?;
 
-    let resp = client
+    let builder = ctx
+        .http
         .post(&endpoint)
         .headers(headers)
-        .json(&req)
-        .send()
+        .json(&req);
+    let resp = retry::send(ctx, builder)
         .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("Provider error {}: {}", status, text));
-    }
+    let resp = check_status(PROVIDER, resp).await?;
 
-    #[derive(Debug, Deserialize)]
-    struct OaChoiceDelta { content: Option<String> }
     #[derive(Debug, Deserialize)]
     struct OaChoiceMsg { content: String }
     #[derive(Debug, Deserialize)]
-    struct OaChoice { delta: Option<OaChoiceDelta>, message: Option<OaChoiceMsg> }
+    struct OaChoice { message: Option<OaChoiceMsg>, #[serde(default)] finish_reason: Option<String> }
     #[derive(Debug, Deserialize)]
-    struct OaResp { choices: Vec<OaChoice> }
+    struct OaResp { choices: Vec<OaChoice>, #[serde(default)] model: Option<String>, #[serde(default)] usage: Option<Usage> }
 
-    let body: OaResp = resp.json().await?;
-    let content = body
+    let body: OaResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let (content, finish_reason) = body
         .choices
         .into_iter()
         .next()
-        .and_then(|c| c.message.map(|m| m.content))
-        .ok_or_else(|| anyhow!("Response has no content"))?;
-    Ok(content)
+        .and_then(|c| c.message.map(|m| (m.content, c.finish_reason)))
+        .ok_or_else(|| ProviderError::malformed(PROVIDER, "response has no content"))?;
+    Ok(ChatResponse { content, usage: body.usage, model: body.model, finish_reason, ..Default::default() })
 }
 
-pub async fn chat_complete_stream<F: FnMut(&str)>(
-    client: &reqwest::Client,
+pub async fn chat_complete_stream(
+    ctx: &ProviderContext<'_>,
     base_url: &str,
     api_key: &str,
-    user_messages: Vec<ChatMessage>,
-    model: Option<&str>,
-    mut on_chunk: F,
-) -> Result<String> {
+    mut req: ChatRequest,
+    on_chunk: &mut OnChunk<'_>,
+) -> Result<ChatResponse> {
     let endpoint = build_endpoint(base_url);
-
-    #[derive(Serialize)]
-    struct StreamReq<'a> { model: &'a str, messages: &'a [ChatMessage], stream: bool }
-    let req = StreamReq { model: model.unwrap_or(""), messages: &user_messages, stream: true };
+    req.stream = Some(true);
+    req.stream_options = Some(StreamOptions { include_usage: true });
 
     let headers = build_headers(api_key)?;
 
-    let resp = client
+    let builder = ctx
+        .http
         .post(&endpoint)
         .headers(headers)
-        .json(&req)
-        .send()
+        .json(&req);
+    let resp = retry::send(ctx, builder)
         .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("Provider error {}: {}", status, text));
-    }
+    let resp = check_status(PROVIDER, resp).await?;
 
-    let mut stream = resp.bytes_stream();
-    let mut buffer = Vec::new();
-    let mut final_text = String::new();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        buffer.extend_from_slice(&chunk);
-        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-            let line_bytes = buffer.drain(..=pos).collect::<Vec<u8>>();
-            let line = String::from_utf8_lossy(&line_bytes).trim().to_string();
-            if line.is_empty() { continue; }
-            let data = if let Some(rest) = line.strip_prefix("data: ") { rest } else if let Some(rest) = line.strip_prefix("data:") { rest } else { continue };
-            if data == "[DONE]" { break; }
-            if let Ok(value) = serde_json::from_str::<Value>(data) {
-                if let Some(s) = value.get("choices").and_then(|v| v.get(0)).and_then(|v| v.get("delta")).and_then(|v| v.get("content")).and_then(|v| v.as_str()) {
-                    if !s.is_empty() { on_chunk(s); final_text.push_str(s); }
-                    continue;
-                }
-                if let Some(s) = value.get("choices").and_then(|v| v.get(0)).and_then(|v| v.get("message")).and_then(|v| v.get("content")).and_then(|v| v.as_str()) {
-                    if !s.is_empty() { on_chunk(s); final_text.push_str(s); }
-                    continue;
-                }
-            }
-        }
-    }
+    Ok(sse::read_chat_stream(PROVIDER, resp.bytes_stream(), on_chunk).await?)
+}
 
-    Ok(final_text)
+pub async fn list_models(ctx: &ProviderContext<'_>, base_url: &str, api_key: &str) -> Result<Vec<ModelInfo>> {
+    #[derive(Deserialize)]
+    struct ModelsResp { data: Vec<ModelInfo> }
+
+    let endpoint = format!("{}{}", base_url.trim_end_matches('/'), MODELS_PATH);
+    let builder = ctx
+        .http
+        .get(&endpoint)
+        .headers(build_headers(api_key)?);
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let resp = check_status(PROVIDER, resp).await?;
+    let body: ModelsResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    Ok(body.data)
 }
@@ -1,12 +1,18 @@
-use anyhow::{anyhow, Result};
-use futures_util::StreamExt;
+use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
-use crate::api::models::{ChatMessage, ChatRequest};
+use crate::api::models::{ChatRequest, ChatResponse, ModelInfo, StreamOptions, Usage};
+use crate::api::{retry, sse};
+use crate::api::providers::{Capabilities, ChatProvider, OnChunk, ProviderContext};
+use crate::errors::{check_status, ProviderError};
 
-const DEFAULT_ENDPOINT: &str = "https://openrouter.ai/api/v1/chat/completions";
+/// Provider name reported in errors
+const PROVIDER: &str = "openrouter";
+const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+const CHAT_PATH: &str = "/chat/completions";
+const MODELS_PATH: &str = "/models";
 const DEFAULT_MODEL: &str = "openrouter/auto";
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,138 +24,127 @@ struct OrChoiceMessage {
 #[derive(Debug, Serialize, Deserialize)]
 struct OrChoice {
     message: OrChoiceMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct OrResponse {
     choices: Vec<OrChoice>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// OpenRouter (`provider = "openrouter"`), the default provider.
+pub struct OpenRouter;
+
+#[async_trait]
+impl ChatProvider for OpenRouter {
+    fn name(&self) -> &'static str { "openrouter" }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { streaming: true, list_models: true, requires_api_key: true, requires_base_url: false, pricing: true }
+    }
+
+    fn api_key_env(&self) -> Option<&'static str> { Some("OPENROUTER_API_KEY") }
+
+    fn default_base_url(&self) -> Option<&'static str> { Some(DEFAULT_BASE_URL) }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse> {
+        chat_complete(ctx, ctx.require_api_key()?, req).await
+    }
+
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse> {
+        chat_complete_stream(ctx, ctx.require_api_key()?, req, on_chunk).await
+    }
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>> {
+        list_models(ctx, ctx.base_url.unwrap_or(DEFAULT_BASE_URL)).await
+    }
 }
 
 pub async fn chat_complete(
-    client: &reqwest::Client,
+    ctx: &ProviderContext<'_>,
     api_key: &str,
-    user_messages: Vec<ChatMessage>,
-    model: Option<&str>,
-) -> Result<String> {
-    let model_name = model.unwrap_or(DEFAULT_MODEL);
-
-    let req = ChatRequest {
-        model: model_name.to_string(),
-        messages: user_messages,
-        stream: None,
-    };
+    mut req: ChatRequest,
+) -> Result<ChatResponse> {
+    if req.model.is_empty() { req.model = DEFAULT_MODEL.to_string(); }
+    req.stream = None;
 
     // Build headers per OpenRouter docs
     let headers = build_headers(api_key)?;
 
-    let resp = client
-        .post(DEFAULT_ENDPOINT)
+    let builder = ctx
+        .http
+        .post(build_endpoint(ctx.base_url.unwrap_or(DEFAULT_BASE_URL), CHAT_PATH))
         .headers(headers)
-        .json(&req)
-        .send()
+        .json(&req);
+    let resp = retry::send(ctx, builder)
         .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("OpenRouter error {}: {}", status, text));
-    }
+    let resp = check_status(PROVIDER, resp).await?;
 
-    let body: OrResponse = resp.json().await?;
-    let content = body
+    let body: OrResponse = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let choice = body
         .choices
         .into_iter()
         .next()
-        .map(|c| c.message.content)
-        .ok_or_else(|| anyhow!("OpenRouter response has no choices"))?;
-    Ok(content)
+        .ok_or_else(|| ProviderError::malformed(PROVIDER, "response has no choices"))?;
+    Ok(ChatResponse { content: choice.message.content, usage: body.usage, model: body.model, finish_reason: choice.finish_reason, ..Default::default() })
 }
 
-pub async fn chat_complete_stream<F: FnMut(&str)>(
-    client: &reqwest::Client,
+pub async fn chat_complete_stream(
+    ctx: &ProviderContext<'_>,
     api_key: &str,
-    user_messages: Vec<ChatMessage>,
-    model: Option<&str>,
-    mut on_chunk: F,
-) -> Result<String> {
-    let model_name = model.unwrap_or(DEFAULT_MODEL);
-
-    // Enable stream
-    #[derive(Serialize)]
-    struct StreamReq<'a> {
-        model: &'a str,
-        messages: &'a [ChatMessage],
-        stream: bool,
-    }
-    let req = StreamReq { model: model_name, messages: &user_messages, stream: true };
+    mut req: ChatRequest,
+    on_chunk: &mut OnChunk<'_>,
+) -> Result<ChatResponse> {
+    if req.model.is_empty() { req.model = DEFAULT_MODEL.to_string(); }
+    // Enable stream, with a trailing usage chunk
+    req.stream = Some(true);
+    req.stream_options = Some(StreamOptions { include_usage: true });
 
     let headers = build_headers(api_key)?;
 
-    let resp = client
-        .post(DEFAULT_ENDPOINT)
+    let builder = ctx
+        .http
+        .post(build_endpoint(ctx.base_url.unwrap_or(DEFAULT_BASE_URL), CHAT_PATH))
         .headers(headers)
-        .json(&req)
-        .send()
+        .json(&req);
+    let resp = retry::send(ctx, builder)
         .await
-        .map_err(|e| anyhow!("Network error: {}", e))?;
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let text = resp.text().await.unwrap_or_default();
-        return Err(anyhow!("OpenRouter error {}: {}", status, text));
-    }
+    let resp = check_status(PROVIDER, resp).await?;
 
-    let mut stream = resp.bytes_stream();
-    let mut buffer = Vec::new();
-    let mut final_text = String::new();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        buffer.extend_from_slice(&chunk);
-        // Split by newlines to process SSE lines
-        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-            let line_bytes = buffer.drain(..=pos).collect::<Vec<u8>>();
-            let line = String::from_utf8_lossy(&line_bytes).trim().to_string();
-            if line.is_empty() { continue; }
-            // Expect "data: ..."
-            let data = if let Some(rest) = line.strip_prefix("data: ") {
-                rest
-            } else if let Some(rest) = line.strip_prefix("data:") {
-                rest
-            } else {
-                continue;
-            };
-            if data == "[DONE]" { break; }
-            // Try parse JSON
-            if let Ok(value) = serde_json::from_str::<Value>(data) {
-                // Prefer OpenAI-style delta { choices[0].delta.content }
-                if let Some(s) = value
-                    .get("choices").and_then(|v| v.get(0))
-                    .and_then(|v| v.get("delta"))
-                    .and_then(|v| v.get("content"))
-                    .and_then(|v| v.as_str())
-                {
-                    if !s.is_empty() { on_chunk(s); final_text.push_str(s); }
-                    continue;
-                }
-                // Fallback: some providers may send message { choices[0].message.content }
-                if let Some(s) = value
-                    .get("choices").and_then(|v| v.get(0))
-                    .and_then(|v| v.get("message"))
-                    .and_then(|v| v.get("content"))
-                    .and_then(|v| v.as_str())
-                {
-                    if !s.is_empty() { on_chunk(s); final_text.push_str(s); }
-                    continue;
-                }
-            }
-        }
-    }
+    Ok(sse::read_chat_stream(PROVIDER, resp.bytes_stream(), on_chunk).await?)
+}
+
+pub async fn list_models(ctx: &ProviderContext<'_>, base_url: &str) -> Result<Vec<ModelInfo>> {
+    #[derive(Deserialize)]
+    struct ModelsResp { data: Vec<ModelInfo> }
 
-    Ok(final_text)
+    let builder = ctx
+        .http
+        .get(build_endpoint(base_url, MODELS_PATH));
+    let resp = retry::send(ctx, builder)
+        .await
+        .map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    let resp = check_status(PROVIDER, resp).await?;
+    let body: ModelsResp = resp.json().await.map_err(|e| ProviderError::from_reqwest(PROVIDER, e))?;
+    Ok(body.data)
 }
 
+fn build_endpoint(base_url: &str, path: &str) -> String { format!("{}{}", base_url.trim_end_matches('/'), path) }
+
 fn build_headers(api_key: &str) -> Result<HeaderMap> {
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -157,10 +152,7 @@ fn build_headers(api_key: &str) -> Result<HeaderMap> {
         HeaderValue::from_str(&format!("Bearer {}", api_key))?,
     );
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-    headers.insert(
-        "HTTP-Referer",
-        HeaderValue::from_static("https://github.com/your-org/spark_cli"),
-    );
+    // App attribution; set `HTTP-Referer` under [http.headers.openrouter] to link a site
     headers.insert("X-Title", HeaderValue::from_static("spark_cli"));
     Ok(headers)
 }
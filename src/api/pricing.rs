@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{ModelInfo, ModelPricing, Usage};
+
+/// Model price in USD per million tokens (`[pricing."<model>"]` in config).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt + usage.completion_tokens as f64 * self.completion) / 1_000_000.0
+    }
+
+    /// Convert OpenRouter's per-token price strings.
+    pub fn from_pricing(pricing: &ModelPricing) -> Option<Self> {
+        let per_token = |s: &Option<String>| s.as_deref().and_then(|v| v.parse::<f64>().ok());
+        let prompt = per_token(&pricing.prompt)?;
+        let completion = per_token(&pricing.completion).unwrap_or(0.0);
+        Some(Self { prompt: prompt * 1_000_000.0, completion: completion * 1_000_000.0 })
+    }
+}
+
+/// Lookup table from model id to price.
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    prices: BTreeMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn new(prices: BTreeMap<String, ModelPrice>) -> Self {
+        Self { prices }
+    }
+
+    /// Prices from a provider's model catalog (only entries that report pricing).
+    pub fn from_models(models: &[ModelInfo]) -> Self {
+        let prices = models
+            .iter()
+            .filter_map(|m| Some((m.id.clone(), ModelPrice::from_pricing(m.pricing.as_ref()?)?)))
+            .collect();
+        Self { prices }
+    }
+
+    /// Exact id first, then without a `vendor/` prefix (so `deepseek-chat` also matches
+    /// `deepseek/deepseek-chat` and vice versa).
+    pub fn lookup(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(p) = self.prices.get(model) { return Some(*p); }
+        let bare = model.rsplit('/').next().unwrap_or(model);
+        self.prices
+            .iter()
+            .find(|(id, _)| id.rsplit('/').next() == Some(bare))
+            .map(|(_, p)| *p)
+    }
+
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.lookup(model).map(|p| p.cost(usage))
+    }
+}
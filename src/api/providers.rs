@@ -1,21 +1,143 @@
-#[derive(Debug, Clone)]
-pub enum Provider {
-    OpenAI,
-    Anthropic,
-    Google,
-    Ollama,
-    OpenRouter,
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder};
+
+use crate::api::models::{ChatRequest, ChatResponse, ModelInfo};
+use crate::api::retry::{RetryHook, RetryPolicy};
+use crate::api::{anthropic, google, mock, ollama, openai_compat, openrouter};
+use crate::config::settings::Settings;
+
+/// Connection details a provider needs for a single call.
+pub struct ProviderContext<'a> {
+    pub http: &'a Client,
+    pub api_key: Option<&'a str>,
+    /// Overrides the provider's default endpoint (required for OpenAI-compatible)
+    pub base_url: Option<&'a str>,
+    pub retry: RetryPolicy,
+    /// Called before each retry wait
+    pub on_retry: Option<&'a RetryHook>,
+    /// Extra headers from `[http.headers.<provider>]`
+    pub headers: Option<&'a HeaderMap>,
+}
+
+impl ProviderContext<'_> {
+    pub fn require_api_key(&self) -> Result<&str> {
+        self.api_key.ok_or_else(|| anyhow!("API key is not set"))
+    }
+
+    /// Add the configured extra headers, after the provider's own so they can override them.
+    pub fn apply_headers(&self, builder: RequestBuilder) -> RequestBuilder {
+        match self.headers {
+            Some(headers) if !headers.is_empty() => builder.headers(headers.clone()),
+            _ => builder,
+        }
+    }
+
+    pub fn require_base_url(&self) -> Result<&str> {
+        self.base_url
+            .ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))
+    }
+}
+
+/// Callback receiving streamed text deltas.
+pub type OnChunk<'a> = dyn FnMut(&str) + Send + 'a;
+
+/// What a provider supports, used by handlers to pick a code path.
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    pub streaming: bool,
+    pub list_models: bool,
+    pub requires_api_key: bool,
+    pub requires_base_url: bool,
+    /// `list_models` reports per-model pricing
+    pub pricing: bool,
+}
+
+/// A chat backend. Implementations live in their own `api::*` module and are
+/// registered by name in [`ProviderRegistry::with_defaults`].
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Environment variable consulted when no api_key is configured
+    fn api_key_env(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Endpoint used when no base_url is configured
+    fn default_base_url(&self) -> Option<&'static str> {
+        None
+    }
+
+    async fn complete(&self, ctx: &ProviderContext<'_>, req: ChatRequest) -> Result<ChatResponse>;
+
+    /// Stream the response, calling `on_chunk` for every text delta. Returns the full text
+    /// plus usage when the stream reports it.
+    async fn stream(
+        &self,
+        ctx: &ProviderContext<'_>,
+        req: ChatRequest,
+        on_chunk: &mut OnChunk<'_>,
+    ) -> Result<ChatResponse>;
+
+    async fn list_models(&self, ctx: &ProviderContext<'_>) -> Result<Vec<ModelInfo>>;
+}
+
+/// Providers keyed by the name used in `provider = "..."` / `--provider`.
+#[derive(Default, Clone)]
+pub struct ProviderRegistry {
+    providers: BTreeMap<String, Arc<dyn ChatProvider>>,
 }
 
-impl Provider {
-    pub fn from_str(value: &str) -> Self {
-        match value.to_lowercase().as_str() {
-            "openai" => Self::OpenAI,
-            "anthropic" => Self::Anthropic,
-            "google" => Self::Google,
-            "ollama" => Self::Ollama,
-            "openrouter" => Self::OpenRouter,
-            _ => Self::OpenRouter,
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every built-in provider and its aliases.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register("openrouter", Arc::new(openrouter::OpenRouter));
+        registry.register("anthropic", Arc::new(anthropic::Anthropic));
+        let gemini: Arc<dyn ChatProvider> = Arc::new(google::Gemini);
+        registry.register("google", gemini.clone());
+        registry.register("gemini", gemini);
+        let compat: Arc<dyn ChatProvider> = Arc::new(openai_compat::OpenAiCompat);
+        for name in ["openai-compatible", "openai", "deepseek", "qwen"] {
+            registry.register(name, compat.clone());
         }
+        registry.register("ollama", Arc::new(ollama::Ollama::default()));
+        registry.register("mock", Arc::new(mock::Mock::default()));
+        registry
+    }
+
+    /// Built-in providers with provider-specific options taken from `settings`.
+    pub fn for_settings(settings: &Settings) -> Self {
+        let mut registry = Self::with_defaults();
+        registry.register("ollama", Arc::new(ollama::Ollama { options: settings.ollama.clone() }));
+        registry.register("mock", Arc::new(mock::Mock { options: settings.mock.clone() }));
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, provider: Arc<dyn ChatProvider>) {
+        self.providers.insert(name.to_lowercase(), provider);
+    }
+
+    /// Look up a provider by name (case-insensitive). An empty name selects OpenRouter.
+    pub fn get(&self, name: &str) -> Result<Arc<dyn ChatProvider>> {
+        let key = if name.trim().is_empty() { "openrouter".to_string() } else { name.trim().to_lowercase() };
+        self.providers.get(&key).cloned().ok_or_else(|| {
+            anyhow!("Unknown provider '{}'. Registered providers: {}", name, self.names().join(", "))
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.providers.keys().map(|s| s.as_str()).collect()
     }
 }
@@ -0,0 +1,147 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::api::providers::ProviderContext;
+
+/// Retry settings for provider calls (`[retry]` in config).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further attempt
+    pub base_delay_ms: u64,
+    /// Upper bound for any single wait, including server-requested ones
+    pub max_delay_ms: u64,
+    /// Randomize backoff delays to avoid synchronized retries
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 500, max_delay_ms: 30_000, jitter: true }
+    }
+}
+
+/// Emitted before sleeping ahead of a retry.
+#[derive(Debug, Clone)]
+pub struct RetryNotice {
+    /// The attempt about to be made (2 for the first retry)
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay: Duration,
+    pub reason: String,
+}
+
+impl RetryNotice {
+    pub fn message(&self) -> String {
+        format!(
+            "{}; retrying in {}s (attempt {}/{})",
+            self.reason,
+            self.delay.as_secs_f32().ceil() as u64,
+            self.attempt,
+            self.max_attempts
+        )
+    }
+}
+
+/// Callback for retry notifications (e.g. to update a spinner).
+pub type RetryHook = dyn Fn(&RetryNotice) + Send + Sync;
+
+impl RetryPolicy {
+    /// Exponential backoff for the given retry number (1 = first retry).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self.base_delay_ms.saturating_mul(1u64 << (retry.saturating_sub(1)).min(20));
+        let capped = exp.min(self.max_delay_ms);
+        let ms = if self.jitter && capped > 1 {
+            // Equal jitter: half fixed, half random
+            capped / 2 + random_u64() % (capped / 2 + 1)
+        } else {
+            capped
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(std::process::id() as u64);
+    hasher.finish()
+}
+
+/// Transient statuses worth retrying (529 is Anthropic's "overloaded").
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 425 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_request()
+}
+
+/// Server-requested wait from `Retry-After`, `retry-after-ms` or `x-ratelimit-reset`.
+pub fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(|s| s.trim().to_string());
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(Duration::from_millis(ms.max(0.0) as u64));
+    }
+    if let Some(v) = header(RETRY_AFTER.as_str()) {
+        if let Ok(secs) = v.parse::<f64>() {
+            return Some(Duration::from_millis((secs.max(0.0) * 1000.0) as u64));
+        }
+        if let Ok(at) = chrono::DateTime::parse_from_rfc2822(&v) {
+            let wait = at.timestamp_millis() - chrono::Utc::now().timestamp_millis();
+            return Some(Duration::from_millis(wait.max(0) as u64));
+        }
+    }
+    if let Some(v) = header("x-ratelimit-reset").and_then(|v| v.parse::<f64>().ok()) {
+        // OpenRouter sends an epoch in ms; others send epoch seconds or a delta in seconds
+        let now_ms = chrono::Utc::now().timestamp_millis() as f64;
+        let wait_ms = if v > 1e12 {
+            v - now_ms
+        } else if v > 1e9 {
+            v * 1000.0 - now_ms
+        } else {
+            v * 1000.0
+        };
+        return Some(Duration::from_millis(wait_ms.max(0.0) as u64));
+    }
+    None
+}
+
+/// Send a request, retrying transient failures per `ctx.retry`.
+///
+/// Only the request/response-head phase is retried: once a success status is
+/// returned the caller owns the body, so nothing is retried after streamed
+/// bytes have been emitted. The last failed response is returned as `Ok` so
+/// callers keep their own error formatting.
+pub async fn send(ctx: &ProviderContext<'_>, builder: RequestBuilder) -> Result<Response, reqwest::Error> {
+    let builder = ctx.apply_headers(builder);
+    let max_attempts = ctx.retry.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        // Bodies we send are plain JSON, so cloning only fails for exotic requests
+        let Some(this_try) = builder.try_clone() else { return builder.send().await };
+        let (delay, reason) = match this_try.send().await {
+            Ok(resp) if attempt < max_attempts && is_retryable_status(resp.status()) => {
+                let delay = server_delay(resp.headers()).unwrap_or_else(|| ctx.retry.backoff(attempt));
+                (delay, format!("Provider returned {}", resp.status()))
+            }
+            Ok(resp) => return Ok(resp),
+            Err(e) if attempt < max_attempts && is_retryable_error(&e) => {
+                (ctx.retry.backoff(attempt), "Network error".to_string())
+            }
+            Err(e) => return Err(e),
+        };
+        let delay = delay.min(Duration::from_millis(ctx.retry.max_delay_ms));
+        attempt += 1;
+        let notice = RetryNotice { attempt, max_attempts, delay, reason };
+        tracing::warn!("{}", notice.message());
+        if let Some(hook) = ctx.on_retry { hook(&notice); }
+        tokio::time::sleep(delay).await;
+    }
+}
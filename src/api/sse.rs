@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+use futures_util::{Stream, StreamExt};
+use serde_json::Value;
+
+use crate::api::models::{ChatResponse, Usage};
+use crate::api::providers::OnChunk;
+use crate::errors::ProviderError;
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// One dispatched server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// `event:` field; `None` means the default `message` type
+    pub event: Option<String>,
+    /// `data:` lines joined with `\n`
+    pub data: String,
+    /// Last `id:` seen on the stream
+    pub id: Option<String>,
+}
+
+/// Incremental decoder for the `text/event-stream` format.
+///
+/// Bytes can be fed in arbitrary chunks; lines may end in LF, CRLF or CR and
+/// may be split across chunks. Comment lines (`:` keepalives) are skipped.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    started: bool,
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+    last_id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self { Self::default() }
+
+    /// Feed a chunk and return the events it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        if !self.started {
+            // Wait until a possible leading BOM is complete
+            if self.buffer.len() < BOM.len() && BOM.starts_with(&self.buffer) { return Vec::new(); }
+            self.started = true;
+            if self.buffer.starts_with(BOM) { self.buffer.drain(..BOM.len()); }
+        }
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            let b = self.buffer[i];
+            if b != b'\n' && b != b'\r' { i += 1; continue; }
+            // A trailing CR may be the first half of a CRLF split across chunks
+            if b == b'\r' && i + 1 == self.buffer.len() { break; }
+            let line = String::from_utf8_lossy(&self.buffer[start..i]).into_owned();
+            i += if b == b'\r' && self.buffer[i + 1] == b'\n' { 2 } else { 1 };
+            start = i;
+            if let Some(ev) = self.process_line(&line) { events.push(ev); }
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flush at end of stream. Unlike the spec, a final event without its
+    /// terminating blank line is still dispatched; some servers omit it.
+    pub fn finish(&mut self) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&rest);
+            let line = line.trim_end_matches('\r').to_string();
+            if let Some(ev) = self.process_line(&line) { events.push(ev); }
+        }
+        if let Some(ev) = self.dispatch() { events.push(ev); }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() { return self.dispatch(); }
+        if line.starts_with(':') { return None; }
+        let (field, value) = match line.split_once(':') {
+            Some((f, v)) => (f, v.strip_prefix(' ').unwrap_or(v)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                if self.has_data { self.data.push('\n'); }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            // `retry` only matters for EventSource reconnection
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if !self.has_data { return None; }
+        self.has_data = false;
+        Some(SseEvent { event, data: std::mem::take(&mut self.data), id: self.last_id.clone() })
+    }
+}
+
+/// Server-sent events read from a byte stream such as `Response::bytes_stream()`.
+pub struct SseStream<S> {
+    inner: Pin<Box<S>>,
+    decoder: SseDecoder,
+    queue: VecDeque<SseEvent>,
+    finished: bool,
+}
+
+impl<S, B> SseStream<S>
+where
+    S: Stream<Item = reqwest::Result<B>>,
+    B: AsRef<[u8]>,
+{
+    pub fn new(inner: S) -> Self {
+        Self { inner: Box::pin(inner), decoder: SseDecoder::new(), queue: VecDeque::new(), finished: false }
+    }
+
+    pub async fn next_event(&mut self) -> Option<reqwest::Result<SseEvent>> {
+        loop {
+            if let Some(ev) = self.queue.pop_front() { return Some(Ok(ev)); }
+            if self.finished { return None; }
+            match self.inner.next().await {
+                Some(Ok(chunk)) => self.queue.extend(self.decoder.feed(chunk.as_ref())),
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.finished = true;
+                    self.queue.extend(self.decoder.finish());
+                }
+            }
+        }
+    }
+}
+
+/// What one OpenAI-style `chat.completion.chunk` event contributes.
+#[derive(Debug, Default, PartialEq)]
+pub struct ChatDelta {
+    pub content: Option<String>,
+    pub finish_reason: Option<String>,
+    pub usage: Option<Usage>,
+    pub model: Option<String>,
+    /// The `[DONE]` sentinel
+    pub done: bool,
+}
+
+/// Interpret an OpenAI-style chat stream event, turning in-stream
+/// `{"error": ...}` payloads (or `event: error`) into a [`ProviderError`].
+pub fn parse_chat_event(provider: &str, event: &SseEvent) -> Result<ChatDelta, ProviderError> {
+    let data = event.data.trim();
+    if data == "[DONE]" { return Ok(ChatDelta { done: true, ..Default::default() }); }
+    let value = match serde_json::from_str::<Value>(data) {
+        Ok(v) => v,
+        Err(_) if event.event.as_deref() == Some("error") => return Err(ProviderError::from_body(provider, data)),
+        Err(e) => {
+            tracing::debug!("{}: skipping non-JSON stream event: {}", provider, e);
+            return Ok(ChatDelta::default());
+        }
+    };
+    if value.get("error").is_some_and(|e| !e.is_null()) || event.event.as_deref() == Some("error") {
+        return Err(ProviderError::from_body(provider, data));
+    }
+    let choice = value.get("choices").and_then(|c| c.get(0));
+    // Deltas normally; some servers send a full `message` instead
+    let content = choice
+        .and_then(|c| c.get("delta").or_else(|| c.get("message")))
+        .and_then(|d| d.get("content"))
+        .and_then(|s| s.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+    let finish_reason = choice.and_then(|c| c.get("finish_reason")).and_then(|f| f.as_str()).map(|s| s.to_string());
+    // OpenRouter reports mid-stream upstream failures as finish_reason "error"
+    if finish_reason.as_deref() == Some("error") {
+        return Err(ProviderError::from_body(provider, data));
+    }
+    let usage = value.get("usage").filter(|u| !u.is_null()).and_then(|u| serde_json::from_value::<Usage>(u.clone()).ok());
+    let model = value.get("model").and_then(|m| m.as_str()).map(|s| s.to_string());
+    Ok(ChatDelta { content, finish_reason, usage, model, done: false })
+}
+
+/// Drive an OpenAI-style chat completion stream to the end, forwarding text to `on_chunk`.
+pub async fn read_chat_stream<S, B>(provider: &str, bytes: S, on_chunk: &mut OnChunk<'_>) -> Result<ChatResponse, ProviderError>
+where
+    S: Stream<Item = reqwest::Result<B>>,
+    B: AsRef<[u8]>,
+{
+    let mut events = SseStream::new(bytes);
+    let mut resp = ChatResponse::default();
+    let mut done = false;
+
+    while let Some(event) = events.next_event().await {
+        let event = event.map_err(|e| ProviderError::from_reqwest(provider, e))?;
+        let delta = parse_chat_event(provider, &event)?;
+        if delta.done { done = true; break; }
+        if let Some(s) = delta.content { on_chunk(&s); resp.content.push_str(&s); }
+        if delta.finish_reason.is_some() { resp.finish_reason = delta.finish_reason; }
+        if delta.usage.is_some() { resp.usage = delta.usage; }
+        if resp.model.is_none() { resp.model = delta.model; }
+    }
+
+    if !done && resp.finish_reason.is_none() {
+        tracing::warn!("{}: stream ended without [DONE] or finish_reason; the response may be truncated", provider);
+    }
+    Ok(resp)
+}
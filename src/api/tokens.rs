@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{ChatMessage, ModelInfo};
+
+/// Tokens a chat API adds around each message (role markers, separators)
+const PER_MESSAGE_TOKENS: u64 = 4;
+/// Tokens that prime the assistant's reply
+const REPLY_PRIMING_TOKENS: u64 = 3;
+
+/// Context window budgeting (`[context]` in config).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    /// Drop the oldest history turns when a request would not fit the model's context
+    pub truncate: bool,
+    /// Room kept for the reply when `max_tokens` is not set
+    pub reserve_tokens: u64,
+    /// Context length for models the catalog does not know; unset skips budgeting for them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u64>,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self { truncate: true, reserve_tokens: 1024, context_length: None }
+    }
+}
+
+impl ContextSettings {
+    /// Budget for `model`: its catalog context length (or the configured fallback),
+    /// minus `max_tokens` or `reserve_tokens` for the reply.
+    pub fn budget(&self, models: Option<&[ModelInfo]>, model: &str, max_tokens: Option<u32>) -> Option<ContextBudget> {
+        let known = models.and_then(|ms| ms.iter().find(|m| m.id == model)).and_then(|m| m.context_length);
+        let context_length = known.or(self.context_length).filter(|n| *n > 0)?;
+        let reserve = max_tokens.map(u64::from).unwrap_or(self.reserve_tokens);
+        Some(ContextBudget { context_length, reserve })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextBudget {
+    pub context_length: u64,
+    /// Tokens kept free for the reply
+    pub reserve: u64,
+}
+
+impl ContextBudget {
+    /// Tokens the prompt may use
+    pub fn available(&self) -> u64 { self.context_length.saturating_sub(self.reserve) }
+}
+
+/// Messages after [`fit`], with what was removed to get there.
+#[derive(Debug, Clone)]
+pub struct Fitted {
+    pub messages: Vec<ChatMessage>,
+    /// History messages left out
+    pub dropped: usize,
+    /// Estimated prompt tokens of `messages`
+    pub estimated: u64,
+}
+
+impl Fitted {
+    /// Still larger than the budget after dropping everything that could go
+    pub fn over(&self, budget: &ContextBudget) -> bool { self.estimated > budget.available() }
+}
+
+/// Rough token count for `text`, calibrated against BPE tokenizers (cl100k/o200k).
+/// Tends to overestimate slightly, which is the safe side for budgeting.
+///
+/// ASCII words cost a token per ~6 characters, punctuation one each, CJK one per
+/// character, and runs of spaces (indentation) one per four.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut tokens = 0u64;
+    let mut word = 0u64; // weighted length of the current word
+    let mut spaces = 0u64;
+    let flush_word = |word: &mut u64, tokens: &mut u64| {
+        if *word > 0 { *tokens += 1 + (*word - 1) / 6; }
+        *word = 0;
+    };
+    for c in text.chars() {
+        if c == ' ' {
+            flush_word(&mut word, &mut tokens);
+            spaces += 1;
+            continue;
+        }
+        // A single space merges into the next word; longer runs are tokens of their own
+        if spaces > 1 { tokens += spaces.div_ceil(4); }
+        spaces = 0;
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word += 1;
+        } else if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            tokens += 1;
+        } else if c.is_alphanumeric() {
+            // Accented and other non-ASCII letters split into more pieces
+            word += 2;
+        } else {
+            flush_word(&mut word, &mut tokens);
+            if c != '\r' { tokens += 1; }
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    if spaces > 1 { tokens += spaces.div_ceil(4); }
+    tokens
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // Hiragana, Katakana
+        | 0x3400..=0x4DBF    // CJK Extension A
+        | 0x4E00..=0x9FFF    // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF    // Hangul syllables
+        | 0xF900..=0xFAFF    // CJK Compatibility Ideographs
+        | 0xFF00..=0xFFEF    // Full-width forms
+        | 0x20000..=0x2FFFF) // CJK Extensions B+
+}
+
+/// Estimated prompt tokens for a chat request carrying `messages`.
+pub fn estimate_messages(messages: &[ChatMessage]) -> u64 {
+    messages.iter().map(|m| PER_MESSAGE_TOKENS + estimate_tokens(&m.role) + estimate_tokens(&m.content)).sum::<u64>() + REPLY_PRIMING_TOKENS
+}
+
+/// Drop the oldest history until `messages` fits `budget`. System messages, messages
+/// with `pinned[i]` set and the last message (the new prompt) always stay. A user
+/// message goes together with the assistant replies that follow it.
+pub fn fit(messages: Vec<ChatMessage>, pinned: &[bool], budget: &ContextBudget) -> Fitted {
+    let last = messages.len().saturating_sub(1);
+    let costs: Vec<u64> = messages.iter().map(|m| estimate_messages(std::slice::from_ref(m)) - REPLY_PRIMING_TOKENS).collect();
+    let mut estimated = costs.iter().sum::<u64>() + REPLY_PRIMING_TOKENS;
+    let keep_always = |i: usize| i == last || messages[i].role == "system" || pinned.get(i).copied().unwrap_or(false);
+    let mut keep = vec![true; messages.len()];
+    let mut i = 0;
+    while estimated > budget.available() && i < last {
+        if keep_always(i) { i += 1; continue; }
+        keep[i] = false;
+        estimated -= costs[i];
+        let was_user = messages[i].role == "user";
+        i += 1;
+        while was_user && i < last && messages[i].role == "assistant" && !keep_always(i) {
+            keep[i] = false;
+            estimated -= costs[i];
+            i += 1;
+        }
+    }
+    let dropped = keep.iter().filter(|k| !**k).count();
+    let messages = messages.into_iter().zip(keep).filter_map(|(m, k)| k.then_some(m)).collect();
+    Fitted { messages, dropped, estimated }
+}
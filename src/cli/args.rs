@@ -1,5 +1,7 @@
 use clap::{Args, Parser, Subcommand};
 
+use crate::api::models::SamplingParams;
+
 #[derive(Parser, Debug)]
 #[command(name = "spark", version, about = "AI-assisted CLI tool", propagate_version = true)]
 pub struct Cli {
@@ -27,7 +29,7 @@ pub struct IoArgs {
     pub output_file: Option<String>,
 }
 
-#[derive(Args, Debug, Default)]
+#[derive(Args, Debug, Clone, Default)]
 pub struct RuntimeArgs {
     /// Override provider for this run
     #[arg(long = "provider")]
@@ -44,6 +46,55 @@ pub struct RuntimeArgs {
     /// Explicit config file path
     #[arg(long = "config")]
     pub config: Option<String>,
+
+    /// Sampling temperature (e.g. 0 for deterministic output)
+    #[arg(long = "temperature")]
+    pub temperature: Option<f32>,
+
+    /// Nucleus sampling probability mass
+    #[arg(long = "top-p")]
+    pub top_p: Option<f32>,
+
+    /// Maximum tokens to generate
+    #[arg(long = "max-tokens")]
+    pub max_tokens: Option<u32>,
+
+    /// Stop sequence (repeatable)
+    #[arg(long = "stop")]
+    pub stop: Vec<String>,
+
+    /// Seed for reproducible sampling where supported
+    #[arg(long = "seed")]
+    pub seed: Option<u64>,
+
+    #[arg(long = "presence-penalty")]
+    pub presence_penalty: Option<f32>,
+
+    #[arg(long = "frequency-penalty")]
+    pub frequency_penalty: Option<f32>,
+
+    /// Bypass the response cache for this run
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// Ignore cached responses but store the new one
+    #[arg(long = "refresh")]
+    pub refresh: bool,
+}
+
+impl RuntimeArgs {
+    /// Sampling values given on the command line; unset flags stay `None`.
+    pub fn sampling_overrides(&self) -> SamplingParams {
+        SamplingParams {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            stop: if self.stop.is_empty() { None } else { Some(self.stop.clone()) },
+            seed: self.seed,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -69,6 +120,66 @@ pub enum Commands {
         #[command(subcommand)]
         action: CodeAction,
     },
+
+    /// List and search the provider's models
+    Models {
+        /// Substring of the model id or name; an exact id shows that model's details
+        query: Option<String>,
+        /// Minimum context window in tokens
+        #[arg(long = "min-context")]
+        min_context: Option<u64>,
+        /// Maximum prompt price in USD per million tokens
+        #[arg(long = "max-price")]
+        max_price: Option<f64>,
+        /// Refetch the model list instead of using the cache
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Send one prompt to several models at once and compare the answers
+    Compare {
+        /// Models to ask, comma-separated; `model@provider` picks another provider
+        #[arg(long, value_delimiter = ',', required = true)]
+        models: Vec<String>,
+        /// Prompt text (or use -f)
+        prompt: Vec<String>,
+        /// Write each answer and a summary.json into this directory
+        #[arg(long = "out-dir")]
+        out_dir: Option<String>,
+        /// Record the answers in the current session; the first successful one
+        /// continues the conversation, the rest are kept as alternatives
+        #[arg(long)]
+        save: bool,
+    },
+
+    /// Run every prompt in a JSONL file, appending one result per line to -o
+    Batch {
+        /// JSONL file with `id`, `prompt` and optional `system`, `model`, `params` per line
+        #[arg(long)]
+        input: String,
+        /// Requests in flight at once
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+        /// Run ids whose earlier result was an error again, replacing those results
+        #[arg(long = "retry-errors")]
+        retry_errors: bool,
+    },
+
+    /// Response cache maintenance
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Token usage and spend across all sessions
+    Usage {
+        /// Group rows by day, model or session
+        #[arg(long, default_value = "day", value_parser = ["day", "model", "session"])]
+        by: String,
+        /// Only count records on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -89,9 +200,112 @@ pub enum ConfigAction {
 #[derive(Subcommand, Debug)]
 pub enum SessionAction {
     New { name: String },
-    List,
+    List {
+        /// Only sessions with this tag (repeat to require several)
+        #[arg(long)]
+        tag: Vec<String>,
+    },
     Load { id: String },
     Delete { id: String },
+    /// Token usage and spend for a session (default: current)
+    Stats { id: Option<String> },
+    /// Print a session's transcript (default: current)
+    Show { id: Option<String> },
+    /// Write a session (default: current) as Markdown, HTML, JSON or JSONL to -o or stdout
+    Export {
+        id: Option<String>,
+        #[arg(long, default_value = "md", value_parser = ["md", "html", "json", "jsonl"])]
+        format: String,
+    },
+    /// Keep message <number> of the current session in context when older turns are dropped
+    Pin { number: usize },
+    /// Undo `session pin`
+    Unpin { number: usize },
+    /// Rename a session (default: current)
+    Rename {
+        name: String,
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Add or remove tags on a session (default: current)
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Pin a provider, model, system prompt or description to a session (default: current); omit the value to clear it
+    Set {
+        #[arg(value_parser = ["provider", "model", "system", "description"])]
+        key: String,
+        value: Option<String>,
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Start a new session from the first <at> messages of session <id>, and switch to it
+    Fork {
+        id: String,
+        #[arg(long)]
+        at: usize,
+        /// Name of the new session (default: "<parent name> (fork)")
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Sessions grouped under the ones they were forked from
+    Tree,
+    /// Find messages across all sessions, newest first
+    Search {
+        query: String,
+        /// Treat the query as a regular expression
+        #[arg(long)]
+        regex: bool,
+        #[arg(long)]
+        case_sensitive: bool,
+        #[arg(long, value_parser = ["user", "assistant", "system"])]
+        role: Option<String>,
+        /// Only messages on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only messages on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Switch to the session of the newest match
+        #[arg(long)]
+        load: bool,
+    },
+    /// Create sessions from a ChatGPT export, an OpenAI messages array or `export --format jsonl`
+    Import {
+        file: String,
+        #[arg(long, value_parser = ["chatgpt", "openai-messages", "spark-jsonl"])]
+        from: String,
+        /// Session name for formats without a title (default: the file name)
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagAction {
+    Add {
+        #[arg(required = true)]
+        tags: Vec<String>,
+        #[arg(long)]
+        id: Option<String>,
+    },
+    Remove {
+        #[arg(required = true)]
+        tags: Vec<String>,
+        #[arg(long)]
+        id: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheAction {
+    /// Entry count, size and age of the response cache
+    Stats,
+    /// Delete every cached response
+    Clear,
 }
 
 #[derive(Subcommand, Debug)]
@@ -1,16 +1,368 @@
-use anyhow::{anyhow, Result};
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::TimeZone;
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use futures_util::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
 
-use crate::api::models::ChatMessage;
-use crate::api::openrouter::{chat_complete as or_chat, chat_complete_stream as or_chat_stream};
-use crate::api::openai_compat::{chat_complete as oa_chat, chat_complete_stream as oa_chat_stream};
+use crate::api::models::{ChatMessage, ChatRequest, ChatResponse, SamplingParams, Usage};
+use crate::api::batch::{self, BatchItem, BatchResult};
+use crate::api::cache::{self, ResponseCache};
+use crate::api::catalog::{self, ModelFilter};
+use crate::api::fallback::FallbackTarget;
+use crate::api::models::ModelInfo;
+use crate::api::pricing::{ModelPrice, PriceTable};
+use crate::api::retry::{RetryNotice, RetryPolicy};
+use crate::api::tokens::{self, ContextSettings};
+use crate::api::providers::{ChatProvider, ProviderContext, ProviderRegistry};
+use reqwest::header::HeaderMap;
 use reqwest::Client;
 use crate::config::settings::Settings;
+use crate::errors::{ErrorInfo, Interrupted, PartialOutput, ProviderError};
 use crate::cli::args::{RuntimeArgs, IoArgs};
-use crate::session::manager::SessionManager;
+use crate::session::export::{self, ExportFormat};
+use crate::session::import::{self, ImportSource};
+use crate::session::search::{self, SearchFilter};
+use crate::session::manager::{SessionManager, SessionMeta};
 use crate::session::history::MessageRecord;
-use crate::utils::code::{extract_code_blocks, choose_best_block, guess_ext_from_lang};
+use crate::session::usage::{day_of, summarize, GroupBy, UsageLog, UsageTotals};
+use crate::utils::code::{extract_code_blocks, guess_ext_from_lang};
+
+/// Provider chosen for this run together with its resolved credentials.
+struct ProviderSelection {
+    provider: Arc<dyn ChatProvider>,
+    /// Name it was selected by, e.g. `deepseek` for the OpenAI-compatible provider
+    label: String,
+    api_key: Option<String>,
+    base_url: Option<String>,
+    model: Option<String>,
+    params: SamplingParams,
+    retry: RetryPolicy,
+    /// Extra headers from `[http.headers.<provider>]`
+    headers: HeaderMap,
+    /// Response cache, when enabled and not bypassed with `--no-cache`
+    cache: Option<ResponseCache>,
+    /// `--refresh`: skip cache lookups but store the new response
+    refresh_cache: bool,
+    context: ContextSettings,
+    /// `[[fallback]]` backends, tried in order (always empty on the fallbacks themselves)
+    fallbacks: Vec<Fallback>,
+}
+
+struct Fallback {
+    target: FallbackTarget,
+    sel: ProviderSelection,
+}
+
+impl ProviderSelection {
+    fn context<'a>(&'a self, http: &'a Client) -> ProviderContext<'a> {
+        ProviderContext {
+            http,
+            api_key: self.api_key.as_deref(),
+            base_url: self.base_url.as_deref(),
+            retry: self.retry,
+            on_retry: None,
+            headers: Some(&self.headers),
+        }
+    }
+
+    /// `label (model)` for messages
+    fn describe(&self) -> String {
+        format!("{} ({})", self.label, self.model.as_deref().filter(|m| !m.is_empty()).unwrap_or("default model"))
+    }
+
+    /// Drop the oldest unpinned history so `messages` fits the model's context window,
+    /// using the cached catalog's context length. Warns when history was dropped or the
+    /// request is still too large; `subject` names the input to blame, e.g. a reviewed file.
+    fn fit_context(&self, messages: Vec<ChatMessage>, pinned: &[bool], subject: Option<&str>) -> Vec<ChatMessage> {
+        let model = self.model.as_deref().unwrap_or_default();
+        let models = catalog::load_cached(self.provider.name(), self.base_url.as_deref());
+        let Some(budget) = self.context.budget(models.as_deref(), model, self.params.max_tokens) else { return messages };
+        let keep_all = vec![true; messages.len()];
+        let fitted = tokens::fit(messages, if self.context.truncate { pinned } else { &keep_all }, &budget);
+        if fitted.dropped > 0 {
+            eprintln!("{}", style(format!(
+                "Left out {} older message(s) to fit {}'s {}-token context (`session pin` keeps a message)",
+                fitted.dropped, model, budget.context_length
+            )).dim());
+        }
+        if fitted.over(&budget) {
+            eprintln!("{}", style(format!(
+                "{} is ~{} tokens, more than the ~{} that fit {}'s {}-token context; the provider will likely reject it",
+                subject.unwrap_or("The request"), fitted.estimated, budget.available(), model, budget.context_length
+            )).yellow());
+        }
+        fitted.messages
+    }
+
+    fn request(&self, messages: Vec<ChatMessage>) -> ChatRequest {
+        ChatRequest { model: self.model.clone().unwrap_or_default(), messages, stream: None, params: self.params.clone(), stream_options: None }
+    }
+
+    /// Estimated USD cost of a response: configured `[pricing]` first, then the
+    /// provider's cached model catalog when it reports prices.
+    async fn cost(&self, settings: &Settings, http: &Client, resp: &ChatResponse) -> Option<f64> {
+        let usage = resp.usage.as_ref()?;
+        let model = resp.model.as_deref().or(self.model.as_deref())?;
+        if let Some(cost) = PriceTable::new(settings.pricing.clone()).cost(model, usage) {
+            return Some(cost);
+        }
+        if !self.provider.capabilities().pricing { return None; }
+        match crate::api::catalog::cached_models(self.provider.as_ref(), &self.context(http), false).await {
+            Ok(models) => PriceTable::from_models(&models).cost(model, usage),
+            Err(e) => { tracing::debug!("price lookup failed: {}", e); None }
+        }
+    }
+
+    /// Session record for an assistant reply, with usage and cost attached.
+    async fn assistant_record(&self, settings: &Settings, http: &Client, resp: &ChatResponse, timestamp_ms: i64) -> MessageRecord {
+        MessageRecord {
+            role: "assistant".into(),
+            content: resp.content.clone(),
+            timestamp_ms,
+            provider: Some(self.label.clone()),
+            model: resp.model.clone().or_else(|| self.model.clone()),
+            usage: if resp.cached { None } else { resp.usage },
+            cost_usd: if resp.cached { None } else { self.cost(settings, http, resp).await },
+            interrupted: resp.interrupted,
+            cached: resp.cached,
+            ..Default::default()
+        }
+    }
+}
+
+/// `command` selects the `[sampling.commands.<command>]` table; CLI flags override both levels.
+/// Fails early when a required key or base_url is missing, or `--model` is not in the
+/// provider's cached model list. Unusable `[[fallback]]` targets are skipped with a warning.
+fn select_provider(settings: &Settings, runtime: &RuntimeArgs, command: &str) -> Result<ProviderSelection> {
+    let mut sel = resolve_provider(settings, runtime, command)?;
+    ensure_usable(&sel)?;
+    if let Some(model) = runtime.model.as_deref() { check_model(&sel, model)?; }
+    for target in &settings.fallback {
+        match resolve_target(settings, runtime, command, target).and_then(|fb| ensure_usable(&fb).map(|_| fb)) {
+            Ok(fb) => sel.fallbacks.push(Fallback { target: target.clone(), sel: fb }),
+            Err(e) => eprintln!("{}", style(format!("Skipping fallback {}: {:#}", target.provider, e)).yellow()),
+        }
+    }
+    Ok(sel)
+}
+
+fn ensure_usable(sel: &ProviderSelection) -> Result<()> {
+    let caps = sel.provider.capabilities();
+    if caps.requires_api_key && sel.api_key.is_none() {
+        let env = sel.provider.api_key_env().map(|var| format!(" or set env {}", var)).unwrap_or_default();
+        return Err(anyhow!("API key is not set. Use `config set api-key ...`{}", env));
+    }
+    if caps.requires_base_url && sel.base_url.is_none() {
+        return Err(anyhow!("Missing base_url in config for {} provider", sel.provider.name()));
+    }
+    Ok(())
+}
+
+/// Validate an explicit `--model` against the cached catalog. Without a cache there is
+/// nothing to check against, so the model is passed through.
+fn check_model(sel: &ProviderSelection, model: &str) -> Result<()> {
+    let Some(models) = catalog::load_cached(sel.provider.name(), sel.base_url.as_deref()) else { return Ok(()) };
+    if models.is_empty() || catalog::contains_model(&models, model) { return Ok(()); }
+    let suggestions = catalog::suggest(&models, model, 3);
+    let mut message = format!("'{}' is not in the cached {} model list", model, sel.provider.name());
+    if !suggestions.is_empty() { message.push_str(&format!(". Did you mean: {}?", suggestions.join(", "))); }
+    Err(ProviderError::ModelNotFound(ErrorInfo { provider: sel.provider.name().to_string(), status: None, message }).into())
+}
+
+/// Provider, credentials and request defaults for this run, without validation.
+/// `--provider` naming another provider than the configured one leaves the top-level
+/// `model` behind along with the key and base_url.
+fn resolve_provider(settings: &Settings, runtime: &RuntimeArgs, command: &str) -> Result<ProviderSelection> {
+    let name = runtime.provider.as_deref().unwrap_or(&settings.provider);
+    let model = runtime.model.clone().or_else(|| settings.model.clone().filter(|_| settings.is_configured_provider(name)));
+    resolve_backend(settings, runtime, command, name, model)
+}
+
+/// A backend next to the primary one (a `[[fallback]]` target or a `compare` model) with
+/// its own key, base_url and model. The top-level `api_key`/`base_url` carry over only
+/// when it names the configured provider.
+fn resolve_target(settings: &Settings, runtime: &RuntimeArgs, command: &str, target: &FallbackTarget) -> Result<ProviderSelection> {
+    let configured = settings.is_configured_provider(&target.provider);
+    // The target becomes the configured provider of the overlay, so its own key and base_url apply
+    let overlay = Settings {
+        provider: target.provider.clone(),
+        api_key: target.api_key.clone().or_else(|| if configured { settings.api_key.clone() } else { None }),
+        base_url: target.base_url.clone().or_else(|| if configured { settings.base_url.clone() } else { None }),
+        ..settings.clone()
+    };
+    resolve_backend(&overlay, runtime, command, &target.provider, target.model.clone())
+}
+
+/// Provider `name` with the key, base_url and sampling that apply to it.
+fn resolve_backend(settings: &Settings, runtime: &RuntimeArgs, command: &str, name: &str, model: Option<String>) -> Result<ProviderSelection> {
+    let provider = ProviderRegistry::for_settings(settings).get(name)?;
+    // The top-level api_key and base_url were written for the configured provider only
+    let configured = settings.is_configured_provider(name);
+    // `openrouter/...` ids, such as the built-in default `openrouter/auto`, exist only on
+    // OpenRouter; other providers get their own default model instead
+    let model = model.filter(|m| provider.name() == "openrouter" || !m.starts_with("openrouter/"));
+
+    // Resolve API key: config first, then the provider's own env var, so OPENROUTER_API_KEY
+    // only ever goes to OpenRouter
+    let api_key = settings
+        .api_key
+        .as_deref()
+        .filter(|s| configured && !s.trim().is_empty())
+        .map(|s| s.to_string())
+        .or_else(|| provider.api_key_env().and_then(|var| std::env::var(var).ok()))
+        .map(|k| crate::utils::secrets::normalize_api_key(&k));
+
+    let params = settings.sampling_for(command).merged(&runtime.sampling_overrides());
+    let headers = settings.http.headers_for(&[provider.name(), name])?;
+    let cache = (settings.cache.enabled && !runtime.no_cache).then(|| ResponseCache::new(settings.cache));
+    Ok(ProviderSelection {
+        provider,
+        label: name.trim().to_lowercase(),
+        api_key,
+        base_url: settings.base_url.clone().filter(|_| configured),
+        model,
+        params,
+        retry: settings.retry,
+        headers,
+        cache,
+        refresh_cache: runtime.refresh,
+        context: settings.context,
+        fallbacks: Vec::new(),
+    })
+}
+
+/// Send a request through the selected provider, or answer it from the response
+/// cache when enabled. When `stream` is set, chunks are printed to stdout as they
+/// arrive (cached replies are replayed the same way); otherwise a spinner shows `spinner_msg`.
+///
+/// Ctrl+C drops the in-flight request and returns what arrived so far with
+/// `interrupted` set, so callers can still save it.
+///
+/// A provider error handled by a `[[fallback]]` target resends the request there,
+/// unless part of the reply was already streamed; the selection that answered is
+/// returned alongside the response.
+async fn request_completion<'a>(sel: &'a ProviderSelection, http: &Client, req: ChatRequest, stream: bool, spinner_msg: &str) -> Result<(ChatResponse, &'a ProviderSelection)> {
+    let mut result = cached_completion(sel, http, req.clone(), stream, spinner_msg).await;
+    let mut answered = sel;
+    for fb in &sel.fallbacks {
+        let Err(e) = &result else { break };
+        if e.downcast_ref::<PartialOutput>().is_some() { break; }
+        let Some(class) = e.downcast_ref::<ProviderError>().map(ProviderError::class) else { break };
+        if !fb.target.handles(class) { continue; }
+        eprintln!("{}", style(format!("{}; falling back to {}", e, fb.sel.describe())).yellow());
+        let req = ChatRequest { model: fb.sel.model.clone().unwrap_or_default(), ..req.clone() };
+        result = cached_completion(&fb.sel, http, req, stream, spinner_msg).await;
+        answered = &fb.sel;
+    }
+    let resp = result?;
+    if !std::ptr::eq(answered, sel) {
+        eprintln!("{}", style(format!("Answered by fallback {}", answered.describe())).dim());
+    }
+    Ok((resp, answered))
+}
+
+async fn cached_completion(sel: &ProviderSelection, http: &Client, req: ChatRequest, stream: bool, spinner_msg: &str) -> Result<ChatResponse> {
+    let Some(cache) = &sel.cache else { return fetch_completion(sel, http, req, stream, spinner_msg).await };
+    let key = cache::cache_key(sel.provider.name(), sel.base_url.as_deref(), &req);
+    if !sel.refresh_cache {
+        if let Some(resp) = cache.get(&key) {
+            eprintln!("{}", style("Using cached response (--refresh to request a new one)").dim());
+            if stream {
+                for piece in resp.content.split_inclusive('\n') { print_chunk(piece); }
+                println!();
+            }
+            return Ok(resp);
+        }
+    }
+    let resp = fetch_completion(sel, http, req, stream, spinner_msg).await?;
+    if let Err(e) = cache.put(&key, sel.provider.name(), &resp) {
+        tracing::warn!("failed to cache response: {:#}", e);
+    }
+    Ok(resp)
+}
+
+fn print_chunk(chunk: &str) {
+    print!("{}", chunk);
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+}
+
+async fn fetch_completion(sel: &ProviderSelection, http: &Client, req: ChatRequest, stream: bool, spinner_msg: &str) -> Result<ChatResponse> {
+    let mut ctx = sel.context(http);
+    if stream && sel.provider.capabilities().streaming {
+        let announce = |n: &RetryNotice| eprintln!("{}", style(n.message()).yellow());
+        ctx.on_retry = Some(&announce);
+        let mut partial = String::new();
+        let mut on_chunk = |chunk: &str| {
+            print_chunk(chunk);
+            partial.push_str(chunk);
+        };
+        let result = tokio::select! {
+            r = sel.provider.stream(&ctx, req, &mut on_chunk) => Some(r),
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        // newline after stream
+        println!();
+        let resp = match result {
+            Some(Ok(resp)) => resp,
+            Some(Err(e)) if !partial.is_empty() => return Err(e.context(PartialOutput)),
+            Some(Err(e)) => return Err(e),
+            None => ChatResponse { content: partial, interrupted: true, ..Default::default() },
+        };
+        log_usage(&resp);
+        warn_if_incomplete(&resp);
+        return Ok(resp);
+    }
+    if stream {
+        eprintln!("{}", style(format!("Provider {} does not support streaming; waiting for full response", sel.provider.name())).yellow());
+    }
+    let pb = ProgressBar::new_spinner().with_message(spinner_msg.to_string());
+    pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    let spinner = pb.clone();
+    let base_msg = spinner_msg.to_string();
+    let update_spinner = move |n: &RetryNotice| spinner.set_message(format!("{} {}", base_msg, n.message()));
+    ctx.on_retry = Some(&update_spinner);
+    let result = tokio::select! {
+        r = sel.provider.complete(&ctx, req) => Some(r),
+        _ = tokio::signal::ctrl_c() => None,
+    };
+    pb.finish_and_clear();
+    let resp = match result {
+        Some(r) => r?,
+        None => ChatResponse { interrupted: true, ..Default::default() },
+    };
+    // Callers treat stream mode as "already printed"
+    if stream { println!("{}", resp.content); }
+    log_usage(&resp);
+    warn_if_incomplete(&resp);
+    Ok(resp)
+}
+
+fn warn_if_incomplete(resp: &ChatResponse) {
+    if resp.interrupted {
+        let msg = if resp.content.is_empty() { "Interrupted" } else { "Interrupted; keeping the partial response" };
+        eprintln!("{}", style(msg).yellow());
+    } else if resp.truncated() {
+        eprintln!("{}", style("Response stopped at the token limit; raise --max-tokens for a complete answer").yellow());
+    }
+}
+
+/// Keep the usage of a reply that no session records, so `spark usage` still counts it.
+fn record_unsaved_usage(record: &MessageRecord) {
+    if let Err(e) = UsageLog::new().append(record) { tracing::warn!("failed to record usage: {:#}", e); }
+}
+
+fn log_usage(resp: &ChatResponse) {
+    if let Some(u) = &resp.usage {
+        tracing::info!(model = resp.model.as_deref().unwrap_or("?"), prompt = u.prompt_tokens, completion = u.completion_tokens, total = u.total_tokens, "token usage");
+    }
+}
 
 fn auto_write_code(text: &str, settings: &Settings, lang_hint: Option<&str>) -> Result<()> {
     let blocks = extract_code_blocks(text);
@@ -43,135 +395,410 @@ fn auto_write_code(text: &str, settings: &Settings, lang_hint: Option<&str>) ->
 
 pub async fn handle_interactive(settings: &Settings, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<()> {
     use dialoguer::Input;
-    println!("{}", style("Interactive mode. Ctrl+C to exit.").cyan());
+    println!("{}", style("Interactive mode. Ctrl+C stops a reply; Ctrl+C at the prompt exits.").cyan());
+    // Conversation memory when no session is active; sessions replay from history.jsonl
+    let mut scratch: Vec<MessageRecord> = Vec::new();
     loop {
-        let line: String = Input::new().with_prompt("You").interact_text()?;
+        let line: String = match Input::new().with_prompt("You").interact_text() {
+            Ok(line) => line,
+            Err(dialoguer::Error::IO(e)) if e.kind() == std::io::ErrorKind::Interrupted => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
         if line.trim().is_empty() { continue; }
-        handle_chat(settings, Some(line), runtime, io, http).await?;
+        let mgr = SessionManager::new();
+        let history = match mgr.current_session_id() {
+            Some(sid) => mgr.load_context(&sid)?,
+            None => scratch.clone(),
+        };
+        let resp = chat_turn(settings, line.clone(), history, runtime, io, http).await?;
+        if mgr.current_session_id().is_none() && !resp.content.is_empty() {
+            scratch.push(MessageRecord { role: "user".into(), content: line, ..Default::default() });
+            scratch.push(MessageRecord { role: "assistant".into(), content: resp.content, ..Default::default() });
+        }
+    }
+}
+
+/// Chat messages for `history` followed by `prompt`, with each one's pinned flag
+/// (the new prompt counts as pinned).
+fn with_prompt(history: &[MessageRecord], prompt: &str) -> (Vec<ChatMessage>, Vec<bool>) {
+    let mut messages: Vec<ChatMessage> = history.iter().map(ChatMessage::from).collect();
+    let mut pinned: Vec<bool> = history.iter().map(|r| r.pinned).collect();
+    messages.push(ChatMessage { role: "user".into(), content: prompt.to_string() });
+    pinned.push(true);
+    (messages, pinned)
+}
+
+/// Prompt from `-f` when given, otherwise the command-line text.
+fn read_prompt(io: &IoArgs, prompt: Option<String>) -> Result<String> {
+    match (&io.input_file, prompt) {
+        (Some(path), _) => Ok(crate::utils::io::read_to_string(path)?.trim().to_string()),
+        (None, Some(p)) if !p.trim().is_empty() => Ok(p),
+        _ => Err(anyhow!("Prompt is empty. Provide text or use interactive/chat mode.")),
     }
 }
 
 pub async fn handle_chat(settings: &Settings, prompt: Option<String>, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<()> {
-    // Prefer file input if provided
-    let prompt = match (&io.input_file, &prompt) {
-        (Some(path), _) => crate::utils::io::read_to_string(path)?.trim().to_string(),
-        (None, Some(p)) if !p.trim().is_empty() => p.to_string(),
-        _ => return Err(anyhow!("Prompt is empty. Provide text or use interactive/chat mode.")),
+    let prompt = read_prompt(io, prompt)?;
+
+    let mgr = SessionManager::new();
+    let history = match mgr.current_session_id() {
+        Some(sid) => mgr.load_context(&sid)?,
+        None => Vec::new(),
     };
+    let resp = chat_turn(settings, prompt, history, runtime, io, http).await?;
+    if resp.interrupted { return Err(Interrupted.into()); }
+    Ok(())
+}
 
-    // Resolve API key: config first, then env OPENROUTER_API_KEY
-    let api_key = settings
-        .api_key
-        .as_deref()
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("OPENROUTER_API_KEY").ok())
-        .ok_or_else(|| anyhow!("API key is not set. Use `config set api-key ...` or set env OPENROUTER_API_KEY"))?;
-    let api_key = crate::utils::secrets::normalize_api_key(&api_key);
-
-    // For now we default to OpenRouter if user says they only have it
-    let provider = runtime.provider.as_deref().unwrap_or(&settings.provider);
-    let model = runtime.model.as_deref().or(settings.model.as_deref());
-
-    if provider.to_lowercase() == "openrouter" || provider.is_empty() {
-        let messages = vec![ChatMessage { role: "user".to_string(), content: prompt.clone() }];
-        let client = http;
-        if runtime.stream {
-            let mut buffer = String::new();
-            let content = or_chat_stream(client, &api_key, messages, model, |chunk| {
-                print!("{}", chunk);
-                let _ = std::io::Write::flush(&mut std::io::stdout());
-                buffer.push_str(chunk);
-            }).await?;
-            // newline after stream
-            println!();
-            let final_text = if content.is_empty() { buffer } else { content };
-            let mgr = SessionManager::new();
-            if let Some(sid) = mgr.current_session_id() {
-                let now = chrono::Utc::now().timestamp_millis();
-                mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt.clone(), timestamp_ms: now })?;
-                mgr.append_message(&sid, &MessageRecord { role: "assistant".into(), content: final_text.clone(), timestamp_ms: now })?;
+/// Send `prompt` after the prior conversation in `history`, record both sides in the
+/// current session and emit the reply. The current session's system prompt and pinned
+/// provider/model apply. An interrupted reply is still recorded (flagged)
+/// unless nothing arrived.
+async fn chat_turn(settings: &Settings, prompt: String, mut history: Vec<MessageRecord>, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<ChatResponse> {
+    let mgr = SessionManager::new();
+    let session = mgr.current_session_id().and_then(|id| mgr.load_meta(&id).ok());
+    let runtime = &match &session {
+        Some(meta) => session_runtime(runtime, meta),
+        None => runtime.clone(),
+    };
+    if let Some(system) = session.as_ref().and_then(|m| m.system.clone()) {
+        history.insert(0, MessageRecord { role: "system".into(), content: system, ..Default::default() });
+    }
+    let sel = select_provider(settings, runtime, "chat")?;
+    let (messages, pinned) = with_prompt(&history, &prompt);
+    let messages = sel.fit_context(messages, &pinned, None);
+    let (resp, answered) = request_completion(&sel, http, sel.request(messages), runtime.stream, "Contacting provider...")
+        .await
+        .context("Request failed")?;
+    if resp.interrupted && resp.content.is_empty() { return Ok(resp); }
+    let content = resp.content.clone();
+
+    // append to session if any
+    let now = chrono::Utc::now().timestamp_millis();
+    let record = answered.assistant_record(settings, http, &resp, now).await;
+    if let Some(sid) = mgr.current_session_id() {
+        mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt, timestamp_ms: now, ..Default::default() })?;
+        mgr.append_message(&sid, &record)?;
+    } else {
+        record_unsaved_usage(&record);
+    }
+    // write to file if requested
+    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &content)?; }
+    else if settings.auto_code_write && !resp.interrupted { auto_write_code(&content, settings, runtime.model.as_deref())?; }
+    if !runtime.stream { println!("{}", content); }
+    Ok(resp)
+}
+
+/// `runtime` with the session's pinned provider and model filled in where the command
+/// line left them out. A pinned model is dropped when `--provider` picks another provider.
+fn session_runtime(runtime: &RuntimeArgs, meta: &SessionMeta) -> RuntimeArgs {
+    let same_provider = match (&runtime.provider, &meta.provider) {
+        (Some(flag), Some(pinned)) => flag.eq_ignore_ascii_case(pinned),
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    RuntimeArgs {
+        provider: runtime.provider.clone().or_else(|| meta.provider.clone()),
+        model: runtime.model.clone().or_else(|| meta.model.clone().filter(|_| same_provider)),
+        ..runtime.clone()
+    }
+}
+
+/// One model's outcome in `spark compare`, also written to `summary.json`.
+#[derive(Serialize)]
+struct CompareRow {
+    model: String,
+    provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    latency_ms: u64,
+    /// Time to the first streamed chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_token_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<Usage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+}
+
+/// `spark compare`: send the prompt (after the current session's history) to every
+/// model concurrently, each streaming into its own progress line, then print the
+/// answers and a latency/token/cost summary. Fails only when every model failed.
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_compare(settings: &Settings, models: &[String], prompt: Option<String>, runtime: &RuntimeArgs, io: &IoArgs, http: &Client, out_dir: Option<&str>, save: bool) -> Result<()> {
+    let prompt = read_prompt(io, prompt)?;
+    let primary = runtime.provider.as_deref().unwrap_or(&settings.provider);
+    // Resolve everything up front so a typo fails before any request is sent
+    let mut sels = Vec::new();
+    for spec in models.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let (model, provider) = spec.rsplit_once('@').unwrap_or((spec, primary));
+        let target = FallbackTarget { provider: provider.to_string(), model: Some(model.to_string()), ..Default::default() };
+        let sel = resolve_target(settings, runtime, "compare", &target).with_context(|| format!("Cannot compare {}", spec))?;
+        ensure_usable(&sel).with_context(|| format!("Cannot compare {}", spec))?;
+        check_model(&sel, model)?;
+        sels.push((spec.to_string(), sel));
+    }
+    if sels.is_empty() { return Err(anyhow!("--models needs at least one model")); }
+
+    let mgr = SessionManager::new();
+    let session = mgr.current_session_id();
+    if save && session.is_none() { return Err(anyhow!("--save needs a current session. Run `session new <name>` first")); }
+    let history = match &session {
+        Some(sid) => mgr.load_context(sid)?,
+        None => Vec::new(),
+    };
+    let (messages, pinned) = with_prompt(&history, &prompt);
+    // Each model gets the history that fits its own context window
+    let requests: Vec<ChatRequest> = sels.iter().map(|(_, sel)| sel.request(sel.fit_context(messages.clone(), &pinned, None))).collect();
+
+    let multi = MultiProgress::new();
+    let width = sels.iter().map(|(spec, _)| spec.chars().count()).max().unwrap_or(0);
+    let pane_style = ProgressStyle::with_template("{spinner} {prefix:.bold} {msg}").unwrap();
+    let runs = sels.iter().zip(requests).map(|((spec, sel), req)| {
+        let pane = multi.add(ProgressBar::new_spinner().with_style(pane_style.clone()).with_prefix(format!("{:<width$}", spec, width = width)));
+        pane.enable_steady_tick(Duration::from_millis(100));
+        compare_one(sel, http, req, pane)
+    });
+    let results = tokio::select! {
+        r = futures_util::future::join_all(runs) => r,
+        _ = tokio::signal::ctrl_c() => {
+            let _ = multi.clear();
+            return Err(Interrupted.into());
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut rows = Vec::new();
+    let mut first_error = None;
+    let mut primary_saved = false;
+    if let Some(dir) = out_dir { std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir))?; }
+    if let (true, Some(sid)) = (save, &session) {
+        mgr.append_message(sid, &MessageRecord { role: "user".into(), content: prompt, timestamp_ms: now, ..Default::default() })?;
+    }
+    for (idx, ((spec, sel), (result, latency, first))) in sels.iter().zip(results).enumerate() {
+        let mut row = CompareRow {
+            model: spec.clone(),
+            provider: sel.label.clone(),
+            error: None,
+            latency_ms: latency.as_millis() as u64,
+            first_token_ms: first.map(|d| d.as_millis() as u64),
+            usage: None,
+            cost_usd: None,
+            file: None,
+        };
+        println!("{}", style(format!("== {} ({}) ==", spec, sel.label)).bold());
+        match result {
+            Ok(resp) => {
+                println!("{}\n", resp.content);
+                let record = sel.assistant_record(settings, http, &resp, now).await;
+                row.usage = resp.usage;
+                row.cost_usd = record.cost_usd;
+                if let Some(dir) = out_dir {
+                    let path = std::path::Path::new(dir).join(format!("{}-{}.md", idx + 1, file_slug(spec)));
+                    std::fs::write(&path, &resp.content)?;
+                    row.file = Some(path.display().to_string());
+                }
+                match (save, &session) {
+                    (true, Some(sid)) => {
+                        mgr.append_message(sid, &MessageRecord { alternative: primary_saved, ..record })?;
+                        primary_saved = true;
+                    }
+                    _ => record_unsaved_usage(&record),
+                }
             }
-            if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &final_text)?; }
-            else if settings.auto_code_write { auto_write_code(&final_text, settings, runtime.model.as_deref())?; }
-            return Ok(());
-        } else {
-            let pb = ProgressBar::new_spinner().with_message("Contacting OpenRouter...");
-            pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            let result = or_chat(client, &api_key, messages, model).await;
-            pb.finish_and_clear();
-            let content = match result {
-                Ok(c) => c,
-                Err(e) => { eprintln!("{}", style(format!("Request failed: {}", e)).red()); return Err(e); }
-            };
-            // append to session if any
-            let mgr = SessionManager::new();
-            if let Some(sid) = mgr.current_session_id() {
-                let now = chrono::Utc::now().timestamp_millis();
-                mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt.clone(), timestamp_ms: now })?;
-                mgr.append_message(&sid, &MessageRecord { role: "assistant".into(), content: content.clone(), timestamp_ms: now })?;
+            Err(e) => {
+                println!("{}\n", style(format!("Error: {:#}", e)).red());
+                row.error = Some(format!("{:#}", e));
+                first_error.get_or_insert(e);
             }
-            // write to file if requested
-            if let Some(out) = &io.output_file { if let Err(e) = crate::utils::io::write_string(out, &content) { eprintln!("{}", style(format!("Failed to write output: {}", e)).red()); } }
-            else if settings.auto_code_write { auto_write_code(&content, settings, runtime.model.as_deref())?; }
-            println!("{}", content);
-            return Ok(());
         }
+        rows.push(row);
+    }
+    print_compare_table(&rows);
+    if let Some(dir) = out_dir {
+        let path = std::path::Path::new(dir).join("summary.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&rows)?)?;
+        eprintln!("Saved {} answer(s) and summary.json to {}", rows.iter().filter(|r| r.file.is_some()).count(), dir);
+    }
+    match first_error {
+        Some(e) if rows.iter().all(|r| r.error.is_some()) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Stream one model's answer into its progress line. Returns the result with the
+/// total latency and the time to the first chunk.
+async fn compare_one(sel: &ProviderSelection, http: &Client, req: ChatRequest, pane: ProgressBar) -> (Result<ChatResponse>, Duration, Option<Duration>) {
+    let mut ctx = sel.context(http);
+    let retry_pane = pane.clone();
+    let announce = move |n: &RetryNotice| retry_pane.set_message(n.message());
+    ctx.on_retry = Some(&announce);
+    let started = Instant::now();
+    let mut first = None;
+    let result = if sel.provider.capabilities().streaming {
+        let mut text = String::new();
+        let mut on_chunk = |chunk: &str| {
+            first.get_or_insert_with(|| started.elapsed());
+            text.push_str(chunk);
+            pane.set_message(preview(&text));
+        };
+        sel.provider.stream(&ctx, req, &mut on_chunk).await
+    } else {
+        pane.set_message("waiting for full response");
+        sel.provider.complete(&ctx, req).await
+    };
+    let latency = started.elapsed();
+    match &result {
+        Ok(resp) => pane.finish_with_message(format!("{} in {:.1}s{}", style("done").green(), latency.as_secs_f64(), tokens_note(resp))),
+        Err(e) => pane.finish_with_message(format!("{} {}", style("failed:").red(), e)),
     }
+    (result, latency, first)
+}
 
-    // OpenAI-compatible providers: deepseek, qwen, openai, custom proxy
-    if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-        let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-        let messages = vec![ChatMessage { role: "user".to_string(), content: prompt.clone() }];
-        if runtime.stream {
-            let mut buffer = String::new();
-            let content = oa_chat_stream(http, base, &api_key, messages, model, |chunk| { print!("{}", chunk); let _ = std::io::Write::flush(&mut std::io::stdout()); buffer.push_str(chunk); }).await?;
-            println!();
-            let final_text = if content.is_empty() { buffer } else { content };
-            let mgr = SessionManager::new();
-            if let Some(sid) = mgr.current_session_id() {
-                let now = chrono::Utc::now().timestamp_millis();
-                mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt.clone(), timestamp_ms: now })?;
-                mgr.append_message(&sid, &MessageRecord { role: "assistant".into(), content: final_text.clone(), timestamp_ms: now })?;
-            }
-            if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &final_text)?; }
-            else if settings.auto_code_write { auto_write_code(&final_text, settings, runtime.model.as_deref())?; }
-            return Ok(());
-        } else {
-            let pb = ProgressBar::new_spinner().with_message("Contacting provider...");
-            pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            let content = oa_chat(http, base, &api_key, messages, model).await?;
-            pb.finish_and_clear();
-            let mgr = SessionManager::new();
-            if let Some(sid) = mgr.current_session_id() {
-                let now = chrono::Utc::now().timestamp_millis();
-                mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt.clone(), timestamp_ms: now })?;
-                mgr.append_message(&sid, &MessageRecord { role: "assistant".into(), content: content.clone(), timestamp_ms: now })?;
+fn tokens_note(resp: &ChatResponse) -> String {
+    resp.usage.map(|u| format!(", {} tokens", u.completion_tokens)).unwrap_or_default()
+}
+
+/// The end of the last line streamed so far, for a one-line progress message.
+fn preview(text: &str) -> String {
+    let line = text.trim_end().lines().last().unwrap_or("");
+    let chars: Vec<char> = line.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(60)..].iter().collect();
+    format!("{} chars | {}", text.chars().count(), tail)
+}
+
+/// `openai/gpt-4o-mini@openrouter` -> `openai_gpt-4o-mini_openrouter`
+fn file_slug(spec: &str) -> String {
+    spec.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect()
+}
+
+fn print_compare_table(rows: &[CompareRow]) {
+    let width = rows.iter().map(|r| r.model.chars().count()).max().unwrap_or(0).max(5);
+    let pwidth = rows.iter().map(|r| r.provider.chars().count()).max().unwrap_or(0).max(8);
+    let secs = |ms: u64| format!("{:.2}s", ms as f64 / 1000.0);
+    println!("{:<width$}  {:<pwidth$}  {:>6}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}", "MODEL", "provider", "status", "latency", "first", "prompt", "completion", "cost", width = width, pwidth = pwidth);
+    for r in rows {
+        let tokens = |f: fn(&Usage) -> u64| r.usage.as_ref().map(|u| f(u).to_string()).unwrap_or_else(|| "-".into());
+        println!(
+            "{:<width$}  {:<pwidth$}  {:>6}  {:>8}  {:>8}  {:>10}  {:>10}  {:>10}",
+            r.model,
+            r.provider,
+            if r.error.is_some() { "error" } else { "ok" },
+            secs(r.latency_ms),
+            r.first_token_ms.map(secs).unwrap_or_else(|| "-".into()),
+            tokens(|u| u.prompt_tokens),
+            tokens(|u| u.completion_tokens),
+            r.cost_usd.map(|c| format!("${:.4}", c)).unwrap_or_else(|| "-".into()),
+            width = width,
+            pwidth = pwidth
+        );
+    }
+}
+
+/// `spark batch`: run every input line not yet in the output file, `concurrency` at a
+/// time, appending each result as it finishes so an interrupted run can be resumed.
+/// Results go to stdout without `-o`. Failed lines are recorded, not fatal.
+pub async fn handle_batch(settings: &Settings, input: &str, concurrency: usize, retry_errors: bool, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<()> {
+    if concurrency == 0 { return Err(anyhow!("--concurrency must be at least 1")); }
+    let items = batch::read_items(std::path::Path::new(input))?;
+    let sel = resolve_provider(settings, runtime, "batch")?;
+    ensure_usable(&sel)?;
+    let models: BTreeSet<&str> = items.iter().filter_map(|i| i.model.as_deref()).chain(runtime.model.as_deref()).collect();
+    for model in models { check_model(&sel, model)?; }
+
+    let mut done = HashSet::new();
+    if let Some(path) = io.output_file.as_deref().map(std::path::Path::new).filter(|p| p.exists()) {
+        let mut existing = batch::read_results(path)?;
+        if retry_errors { existing.retain(|r| r.error.is_none()); }
+        // Rewrite before appending: drops retried errors and any line torn by an interrupted run
+        let mut content = String::new();
+        for r in &existing { content.push_str(&serde_json::to_string(r)?); content.push('\n'); }
+        std::fs::write(path, content).with_context(|| format!("Failed to rewrite {}", path.display()))?;
+        done.extend(existing.into_iter().map(|r| r.id));
+    }
+    let pending: Vec<&BatchItem> = items.iter().filter(|i| !done.contains(&i.id)).collect();
+    if pending.len() < items.len() {
+        eprintln!("Skipping {} of {} item(s) already in the output", items.len() - pending.len(), items.len());
+    }
+    if pending.is_empty() { return Ok(()); }
+
+    let mut sink: Box<dyn Write> = match io.output_file.as_deref() {
+        Some(path) => Box::new(std::fs::OpenOptions::new().create(true).append(true).open(path).with_context(|| format!("Failed to open {}", path))?),
+        None => Box::new(std::io::stdout()),
+    };
+    let pb = ProgressBar::new(pending.len() as u64);
+    pb.set_style(ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} [{elapsed_precise}<{eta_precise}] {msg}").unwrap());
+    let mut runs = futures_util::stream::iter(pending).map(|item| batch_one(&sel, settings, http, item)).buffer_unordered(concurrency);
+    let (mut written, mut failed) = (0, 0);
+    loop {
+        let next = tokio::select! {
+            r = runs.next() => r,
+            _ = tokio::signal::ctrl_c() => {
+                pb.abandon_with_message("interrupted");
+                eprintln!("{}", style(format!("Interrupted after {} result(s); run the same command again to resume", written)).yellow());
+                return Err(Interrupted.into());
             }
-            if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &content)?; }
-            else if settings.auto_code_write { auto_write_code(&content, settings, runtime.model.as_deref())?; }
-            println!("{}", content);
-            return Ok(());
+        };
+        let Some(result) = next else { break };
+        if result.error.is_some() {
+            failed += 1;
+            pb.set_message(style(format!("{} failed", failed)).red().to_string());
         }
+        writeln!(sink, "{}", serde_json::to_string(&result)?)?;
+        sink.flush()?;
+        written += 1;
+        pb.inc(1);
     }
-
-    eprintln!("{}", style("Selected provider not supported yet; falling back to OpenRouter").yellow());
-    let messages = vec![ChatMessage { role: "user".to_string(), content: prompt.clone() }];
-    let client = http;
-    let content = or_chat(client, &api_key, messages, None).await?;
-    let mgr = SessionManager::new();
-    if let Some(sid) = mgr.current_session_id() {
-        let now = chrono::Utc::now().timestamp_millis();
-        mgr.append_message(&sid, &MessageRecord { role: "user".into(), content: prompt, timestamp_ms: now })?;
-        mgr.append_message(&sid, &MessageRecord { role: "assistant".into(), content: content.clone(), timestamp_ms: now })?;
+    pb.finish_and_clear();
+    eprintln!("{} succeeded, {} failed", written - failed, failed);
+    if failed > 0 {
+        eprintln!("{}", style("Errors are recorded in the output; rerun with --retry-errors to try them again").yellow());
     }
-    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &content)?; }
-    else if settings.auto_code_write { auto_write_code(&content, settings, runtime.model.as_deref())?; }
-    println!("{}", content);
     Ok(())
 }
 
+async fn batch_one(sel: &ProviderSelection, settings: &Settings, http: &Client, item: &BatchItem) -> BatchResult {
+    let model = item.model.clone().or_else(|| sel.model.clone()).unwrap_or_default();
+    let req = ChatRequest { model: model.clone(), messages: item.messages(), stream: None, params: sel.params.merged(&item.params), stream_options: None };
+    let started = Instant::now();
+    let result = quiet_completion(sel, http, req).await;
+    let mut out = BatchResult { id: item.id.clone(), latency_ms: started.elapsed().as_millis() as u64, ..Default::default() };
+    match result {
+        Ok(resp) => {
+            let record = sel.assistant_record(settings, http, &resp, chrono::Utc::now().timestamp_millis()).await;
+            record_unsaved_usage(&record);
+            out.cost_usd = record.cost_usd;
+            out.usage = record.usage;
+            out.model = resp.model.or(Some(model)).filter(|m| !m.is_empty());
+            out.content = Some(resp.content);
+            out.finish_reason = resp.finish_reason;
+            out.cached = resp.cached;
+        }
+        Err(e) => {
+            out.model = Some(model).filter(|m| !m.is_empty());
+            out.error_class = e.downcast_ref::<ProviderError>().map(ProviderError::class);
+            out.error = Some(format!("{:#}", e));
+        }
+    }
+    out
+}
+
+/// A completion with the response cache but without any terminal output, for
+/// requests running in parallel under one progress bar.
+async fn quiet_completion(sel: &ProviderSelection, http: &Client, req: ChatRequest) -> Result<ChatResponse> {
+    let key = sel.cache.as_ref().map(|_| cache::cache_key(sel.provider.name(), sel.base_url.as_deref(), &req));
+    if let (Some(cache), Some(key), false) = (&sel.cache, &key, sel.refresh_cache) {
+        if let Some(resp) = cache.get(key) { return Ok(resp); }
+    }
+    let resp = sel.provider.complete(&sel.context(http), req).await?;
+    if let (Some(cache), Some(key)) = (&sel.cache, &key) {
+        if let Err(e) = cache.put(key, sel.provider.name(), &resp) { tracing::warn!("failed to cache response: {:#}", e); }
+    }
+    Ok(resp)
+}
+
 pub async fn handle_config_list(settings: &Settings) -> Result<()> {
     println!("Current provider: {}", settings.provider);
     println!("API key set: {}", settings.api_key.is_some());
@@ -196,13 +823,84 @@ pub async fn handle_session_new(_settings: &Settings, name: &str) -> Result<()>
     Ok(())
 }
 
-pub async fn handle_session_list(_settings: &Settings) -> Result<()> {
+pub async fn handle_session_list(_settings: &Settings, tags: &[String]) -> Result<()> {
     let mgr = SessionManager::new();
     let list = mgr.list_sessions()?;
     let current = mgr.current_session_id();
-    for meta in list {
+    for meta in list.into_iter().filter(|m| tags.iter().all(|t| m.tags.contains(t))) {
         let mark = if current.as_deref() == Some(&meta.id) { "*" } else { " " };
-        println!("{} {} - {}", mark, meta.id, meta.name);
+        let active = chrono::Local
+            .timestamp_millis_opt(meta.last_active_ms())
+            .single()
+            .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let tags: Vec<String> = meta.tags.iter().map(|t| format!("#{}", t)).collect();
+        if tags.is_empty() { println!("{} {} {} - {}", mark, meta.id, style(active).dim(), meta.name); }
+        else { println!("{} {} {} - {} {}", mark, meta.id, style(active).dim(), meta.name, style(tags.join(" ")).dim()); }
+        if let Some(description) = &meta.description { println!("    {}", style(description).dim()); }
+    }
+    Ok(())
+}
+
+/// Id of the session `id`, or of the current one.
+fn target_session(mgr: &SessionManager, id: Option<&str>) -> Result<String> {
+    match id {
+        Some(id) => Ok(id.to_string()),
+        None => mgr.current_session_id().ok_or_else(|| anyhow!("No current session. Pass --id or run `session load <id>`")),
+    }
+}
+
+pub async fn handle_session_rename(_settings: &Settings, name: &str, id: Option<&str>) -> Result<()> {
+    if name.trim().is_empty() { return Err(anyhow!("Session name cannot be empty")); }
+    let mgr = SessionManager::new();
+    let id = target_session(&mgr, id)?;
+    let mut old = String::new();
+    mgr.update_meta(&id, |meta| {
+        old = std::mem::replace(&mut meta.name, name.trim().to_string());
+        Ok(())
+    })?;
+    println!("Renamed session {}: {} -> {}", id, old, name.trim());
+    Ok(())
+}
+
+pub async fn handle_session_tag(_settings: &Settings, tags: &[String], add: bool, id: Option<&str>) -> Result<()> {
+    for tag in tags {
+        if tag.is_empty() || tag.chars().any(|c| c.is_whitespace() || c == ',' || c == '#') {
+            return Err(anyhow!("Invalid tag '{}': tags cannot be empty or contain spaces, commas or '#'", tag));
+        }
+    }
+    let mgr = SessionManager::new();
+    let id = target_session(&mgr, id)?;
+    let meta = mgr.update_meta(&id, |meta| {
+        for tag in tags {
+            if add && !meta.tags.contains(tag) { meta.tags.push(tag.clone()); }
+            if !add { meta.tags.retain(|t| t != tag); }
+        }
+        Ok(())
+    })?;
+    let list: Vec<String> = meta.tags.iter().map(|t| format!("#{}", t)).collect();
+    println!("Tags of {}: {}", id, if list.is_empty() { "(none)".to_string() } else { list.join(" ") });
+    Ok(())
+}
+
+pub async fn handle_session_set(_settings: &Settings, key: &str, value: Option<&str>, id: Option<&str>) -> Result<()> {
+    let mgr = SessionManager::new();
+    let id = target_session(&mgr, id)?;
+    let value = value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    mgr.update_meta(&id, |meta| {
+        let field = match key {
+            "provider" => &mut meta.provider,
+            "model" => &mut meta.model,
+            "system" => &mut meta.system,
+            "description" => &mut meta.description,
+            other => return Err(anyhow!("Unknown session setting: {}", other)),
+        };
+        *field = value.clone();
+        Ok(())
+    })?;
+    match value {
+        Some(v) => println!("Set {} of {} to {}", key, id, v),
+        None => println!("Cleared {} of {}", key, id),
     }
     Ok(())
 }
@@ -221,17 +919,336 @@ pub async fn handle_session_delete(_settings: &Settings, id: &str) -> Result<()>
     Ok(())
 }
 
-pub async fn handle_code_generate(settings: &Settings, lang: &str, kind: &str, runtime: &RuntimeArgs, io: &IoArgs, http: &Client, code_only: bool, out_dir: &Option<String>) -> Result<()> {
-    let api_key = settings
-        .api_key
-        .as_deref()
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("OPENROUTER_API_KEY").ok())
-        .ok_or_else(|| anyhow!("API key is not set. Use `config set api-key ...` or set env OPENROUTER_API_KEY"))?;
-    let api_key = crate::utils::secrets::normalize_api_key(&api_key);
+pub async fn handle_session_fork(_settings: &Settings, id: &str, at: usize, name: Option<&str>) -> Result<()> {
+    let mgr = SessionManager::new();
+    let fork = mgr.fork_session(id, at, name)?;
+    mgr.set_current_session_id(&fork.id)?;
+    println!("Forked {} at message {} -> {} ({})", id, at, fork.id, fork.name);
+    Ok(())
+}
+
+pub async fn handle_session_tree(_settings: &Settings) -> Result<()> {
+    let mgr = SessionManager::new();
+    let mut sessions = mgr.list_sessions()?;
+    // Lineage follows creation: newest first at the top level, branches oldest first
+    sessions.sort_by_key(|m| std::cmp::Reverse(m.created_ms));
+    let current = mgr.current_session_id();
+    let ids: HashSet<&str> = sessions.iter().map(|m| m.id.as_str()).collect();
+    let mut children: std::collections::HashMap<&str, Vec<&SessionMeta>> = std::collections::HashMap::new();
+    let mut roots = Vec::new();
+    for meta in &sessions {
+        match meta.parent.as_deref() {
+            Some(parent) if ids.contains(parent) => children.entry(parent).or_default().insert(0, meta),
+            _ => roots.push(meta),
+        }
+    }
+    for root in roots {
+        print_tree_node(&mgr, root, &children, current.as_deref(), "", None)?;
+    }
+    Ok(())
+}
+
+/// One line per session: `prefix` carries the branch lines of its ancestors, `last`
+/// is whether it is the final child (`None` for a top-level session).
+fn print_tree_node(
+    mgr: &SessionManager,
+    meta: &SessionMeta,
+    children: &std::collections::HashMap<&str, Vec<&SessionMeta>>,
+    current: Option<&str>,
+    prefix: &str,
+    last: Option<bool>,
+) -> Result<()> {
+    let mark = if current == Some(meta.id.as_str()) { "*" } else { " " };
+    let branch = match last { None => "", Some(true) => "└─ ", Some(false) => "├─ " };
+    let count = mgr.load_history(&meta.id)?.len();
+    let mut info = format!("{} message(s)", count);
+    match (&meta.parent, meta.fork_at) {
+        (Some(_), Some(at)) if last.is_some() => info = format!("at #{}, {}", at, info),
+        // The parent was deleted
+        (Some(parent), Some(at)) => info = format!("from {} at #{}, {}", parent, at, info),
+        _ => {}
+    }
+    println!("{} {}{}{} - {} {}", mark, prefix, branch, meta.id, meta.name, style(format!("({})", info)).dim());
+    let Some(kids) = children.get(meta.id.as_str()) else { return Ok(()) };
+    let prefix = match last { None => prefix.to_string(), Some(true) => format!("{}   ", prefix), Some(false) => format!("{}│  ", prefix) };
+    for (idx, kid) in kids.iter().enumerate() {
+        print_tree_node(mgr, kid, children, current, &prefix, Some(idx + 1 == kids.len()))?;
+    }
+    Ok(())
+}
+
+pub async fn handle_session_search(
+    _settings: &Settings,
+    query: &str,
+    regex: bool,
+    case_sensitive: bool,
+    filter: &SearchFilter,
+    limit: usize,
+    load: bool,
+) -> Result<()> {
+    for day in [&filter.since, &filter.until].into_iter().flatten() {
+        chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| anyhow!("Dates must be YYYY-MM-DD, got {}", day))?;
+    }
+    let pattern = search::pattern(query, regex, case_sensitive)?;
+    let mgr = SessionManager::new();
+    let hits = search::search(&mgr, &pattern, filter)?;
+    if hits.is_empty() {
+        println!("No matches");
+        return Ok(());
+    }
+    for hit in hits.iter().take(limit) {
+        let when = chrono::Local
+            .timestamp_millis_opt(hit.record.timestamp_ms)
+            .single()
+            .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{} {} {} {}",
+            style(&hit.session.id).dim(),
+            style(&hit.session.name).bold(),
+            style(format!("#{} {}", hit.number, export::role_title(&hit.record.role))).cyan(),
+            style(when).dim()
+        );
+        let (before, matched, after) = search::snippet(&hit.record.content, hit.range.clone(), 60);
+        println!("  {}{}{}", before, style(matched).yellow().bold(), after);
+    }
+    let sessions: HashSet<&str> = hits.iter().map(|h| h.session.id.as_str()).collect();
+    let shown = if hits.len() > limit { format!(", showing the newest {}", limit) } else { String::new() };
+    println!("{}", style(format!("{} match(es) in {} session(s){}", hits.len(), sessions.len(), shown)).dim());
+    if load {
+        mgr.set_current_session_id(&hits[0].session.id)?;
+        println!("Switched to session {}", hits[0].session.id);
+    }
+    Ok(())
+}
 
-    let provider = runtime.provider.as_deref().unwrap_or(&settings.provider);
-    let model = runtime.model.as_deref().or(settings.model.as_deref());
+/// The session `id`, or the current one, with its metadata.
+fn find_session(mgr: &SessionManager, id: Option<&str>) -> Result<SessionMeta> {
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => mgr.current_session_id().ok_or_else(|| anyhow!("No current session. Pass an id or run `session load <id>`"))?,
+    };
+    mgr.list_sessions()?
+        .into_iter()
+        .find(|m| m.id == id)
+        .ok_or_else(|| anyhow!("Session {} not found", id))
+}
+
+pub async fn handle_session_stats(_settings: &Settings, id: Option<&str>) -> Result<()> {
+    let mgr = SessionManager::new();
+    let meta = find_session(&mgr, id)?;
+    let records = mgr.load_history(&meta.id)?;
+    let rows = summarize(records.iter().map(|r| (&meta, r)), GroupBy::Model);
+    println!("Session {} - {}", meta.id, meta.name);
+    print_usage_table("model", &rows);
+    Ok(())
+}
+
+pub async fn handle_session_show(_settings: &Settings, id: Option<&str>) -> Result<()> {
+    let mgr = SessionManager::new();
+    let meta = find_session(&mgr, id)?;
+    let records = mgr.load_history(&meta.id)?;
+    println!("{} {}", style(&meta.name).bold(), style(format!("({}, {} message(s))", meta.id, records.len())).dim());
+    for (key, value) in export::settings_lines(&meta) {
+        println!("{}", style(format!("{}: {}", key, value)).dim());
+    }
+    for (idx, record) in records.iter().enumerate() {
+        let role = export::role_title(&record.role);
+        let role = match record.role.as_str() {
+            "user" => style(role).cyan().bold(),
+            "assistant" => style(role).green().bold(),
+            _ => style(role).yellow().bold(),
+        };
+        let when = chrono::Local
+            .timestamp_millis_opt(record.timestamp_ms)
+            .single()
+            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let details = export::details(record);
+        println!("\n{} {} {}", style(format!("#{}", idx + 1)).dim(), role, style(when).dim());
+        if !details.is_empty() { println!("{}", style(details).dim()); }
+        println!("{}", record.content.trim_end());
+    }
+    Ok(())
+}
+
+pub async fn handle_session_export(_settings: &Settings, id: Option<&str>, format: &str, io: &IoArgs) -> Result<()> {
+    let format = ExportFormat::parse(format).ok_or_else(|| anyhow!("Unknown export format: {}", format))?;
+    let mgr = SessionManager::new();
+    let meta = find_session(&mgr, id)?;
+    let records = mgr.load_history(&meta.id)?;
+    let document = export::render(&meta, &records, format)?;
+    match &io.output_file {
+        Some(out) => {
+            crate::utils::io::write_string(out, &document)?;
+            eprintln!("Exported {} message(s) to {}", records.len(), out);
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+pub async fn handle_session_pin(_settings: &Settings, number: usize, pinned: bool) -> Result<()> {
+    let mgr = SessionManager::new();
+    let id = mgr.current_session_id().ok_or_else(|| anyhow!("No current session. Run `session load <id>` first"))?;
+    let record = mgr.set_pinned(&id, number, pinned)?;
+    let preview: String = record.content.chars().take(60).collect();
+    println!("{} message {} ({}): {}", if pinned { "Pinned" } else { "Unpinned" }, number, record.role, preview.replace('\n', " "));
+    Ok(())
+}
+
+pub async fn handle_session_import(_settings: &Settings, file: &str, from: &str, name: Option<&str>) -> Result<()> {
+    let source = ImportSource::parse(from).ok_or_else(|| anyhow!("Unknown import format: {}", from))?;
+    let content = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+    let fallback_name = name.map(str::to_string).unwrap_or_else(|| {
+        std::path::Path::new(file).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| file.to_string())
+    });
+    let sessions = import::parse(source, &content, &fallback_name, chrono::Utc::now().timestamp_millis())?;
+    let mgr = SessionManager::new();
+    let (mut imported, mut empty) = (0, 0);
+    let mut skipped = std::collections::BTreeMap::<String, usize>::new();
+    for session in sessions {
+        for (kind, n) in &session.skipped { *skipped.entry(kind.clone()).or_default() += n; }
+        if session.records.is_empty() { empty += 1; continue; }
+        let id = mgr.import_session(session.meta.clone(), &session.records)?;
+        println!("{} - {} ({} message(s))", id, session.meta.name, session.records.len());
+        imported += 1;
+    }
+    println!("Imported {} session(s)", imported);
+    if empty > 0 { println!("{}", style(format!("Left out {} conversation(s) with no text messages", empty)).dim()); }
+    if !skipped.is_empty() {
+        let list: Vec<String> = skipped.iter().map(|(kind, n)| format!("{} {}(s)", n, kind)).collect();
+        println!("{}", style(format!("Skipped non-text content: {}", list.join(", "))).yellow());
+    }
+    Ok(())
+}
+
+pub async fn handle_usage(_settings: &Settings, by: &str, since: Option<&str>) -> Result<()> {
+    let group = GroupBy::parse(by).ok_or_else(|| anyhow!("Unknown grouping: {}", by))?;
+    if let Some(day) = since {
+        chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| anyhow!("--since expects YYYY-MM-DD, got {}", day))?;
+    }
+    let mgr = SessionManager::new();
+    let mut entries = Vec::new();
+    for meta in mgr.list_sessions()? {
+        let records = mgr.load_history(&meta.id)?;
+        entries.push((meta, records));
+    }
+    // Calls outside any session, under a meta without an id
+    entries.push((SessionMeta::default(), UsageLog::new().load()?));
+    // ISO dates compare correctly as strings
+    let rows = summarize(
+        entries.iter().flat_map(|(meta, records)| records.iter().map(move |r| (meta, r)))
+            .filter(|(_, r)| since.map(|d| day_of(r.timestamp_ms).as_str() >= d).unwrap_or(true)),
+        group,
+    );
+    print_usage_table(by, &rows);
+    Ok(())
+}
+
+pub async fn handle_cache_stats(settings: &Settings) -> Result<()> {
+    let cache = ResponseCache::new(settings.cache);
+    let stats = cache.stats()?;
+    let when = |ms: Option<i64>| {
+        ms.and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".into())
+    };
+    println!("Cache:    {} ({})", if settings.cache.enabled { "enabled" } else { "disabled" }, cache.dir().display());
+    println!("Entries:  {} ({} expired)", stats.entries, stats.expired);
+    println!("Size:     {:.1} KiB of {} MiB", stats.bytes as f64 / 1024.0, settings.cache.max_size_mb);
+    println!("TTL:      {}", if settings.cache.ttl_secs == 0 { "none".to_string() } else { format!("{}s", settings.cache.ttl_secs) });
+    println!("Oldest:   {}", when(stats.oldest_ms));
+    println!("Newest:   {}", when(stats.newest_ms));
+    Ok(())
+}
+
+pub async fn handle_cache_clear(settings: &Settings) -> Result<()> {
+    let removed = ResponseCache::new(settings.cache).clear()?;
+    println!("Removed {} cached response(s)", removed);
+    Ok(())
+}
+
+fn print_usage_table(label: &str, rows: &std::collections::BTreeMap<String, UsageTotals>) {
+    if rows.is_empty() {
+        println!("No usage recorded yet.");
+        return;
+    }
+    let width = rows.keys().map(|k| k.chars().count()).max().unwrap_or(0).max(label.len());
+    println!("{:<width$}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}", label, "requests", "prompt", "completion", "total", "cost", width = width);
+    let mut total = UsageTotals::default();
+    for (key, t) in rows {
+        println!("{:<width$}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}", key, t.requests, t.prompt_tokens, t.completion_tokens, t.total_tokens, format_cost(t), width = width);
+        total.merge(t);
+    }
+    println!("{:<width$}  {:>8}  {:>10}  {:>10}  {:>10}  {:>10}", "TOTAL", total.requests, total.prompt_tokens, total.completion_tokens, total.total_tokens, format_cost(&total), width = width);
+    if total.unpriced > 0 {
+        eprintln!("{}", style(format!("{} request(s) had no known price; add them under [pricing] in config", total.unpriced)).yellow());
+    }
+}
+
+fn format_cost(t: &UsageTotals) -> String {
+    let mark = if t.unpriced > 0 { "*" } else { "" };
+    format!("${:.4}{}", t.cost_usd, mark)
+}
+
+/// `spark models`: list the provider's models matching `filter`. A query equal to a
+/// model id prints that model's details instead.
+pub async fn handle_models(settings: &Settings, runtime: &RuntimeArgs, http: &Client, filter: &ModelFilter, refresh: bool) -> Result<()> {
+    let sel = resolve_provider(settings, runtime, "chat")?;
+    if !sel.provider.capabilities().list_models {
+        return Err(anyhow!("Provider {} cannot list models", sel.provider.name()));
+    }
+    let models = catalog::cached_models(sel.provider.as_ref(), &sel.context(http), refresh).await?;
+    if let Some(m) = filter.query.as_deref().and_then(|q| models.iter().find(|m| m.id == q)) {
+        print_model_details(m);
+        return Ok(());
+    }
+    let mut matches: Vec<&ModelInfo> = models.iter().filter(|m| filter.matches(m)).collect();
+    matches.sort_by(|a, b| a.id.cmp(&b.id));
+    if matches.is_empty() {
+        println!("No models match.");
+        if let Some(q) = &filter.query {
+            let suggestions = catalog::suggest(&models, q, 5);
+            if !suggestions.is_empty() { println!("Did you mean: {}", suggestions.join(", ")); }
+        }
+        return Ok(());
+    }
+    let width = matches.iter().map(|m| m.id.chars().count()).max().unwrap_or(0).max(5);
+    println!("{:<width$}  {:>9}  {:>10}  {:>10}  modality", "MODEL", "context", "prompt/M", "compl/M", width = width);
+    for m in &matches {
+        let price = m.pricing.as_ref().and_then(ModelPrice::from_pricing);
+        println!(
+            "{:<width$}  {:>9}  {:>10}  {:>10}  {}",
+            m.id,
+            m.context_length.map(|c| c.to_string()).unwrap_or_else(|| "-".into()),
+            price.map(|p| format_price(p.prompt)).unwrap_or_else(|| "-".into()),
+            price.map(|p| format_price(p.completion)).unwrap_or_else(|| "-".into()),
+            m.modality().unwrap_or_else(|| "-".into()),
+            width = width
+        );
+    }
+    println!("{} model(s)", matches.len());
+    Ok(())
+}
+
+fn print_model_details(m: &ModelInfo) {
+    println!("{}", style(&m.id).bold());
+    if let Some(name) = &m.name { println!("  name:        {}", name); }
+    if let Some(ctx) = m.context_length { println!("  context:     {} tokens", ctx); }
+    if let Some(price) = m.pricing.as_ref().and_then(ModelPrice::from_pricing) {
+        println!("  pricing:     {} prompt / {} completion per million tokens", format_price(price.prompt), format_price(price.completion));
+    }
+    if let Some(modality) = m.modality() { println!("  modality:    {}", modality); }
+}
+
+fn format_price(usd_per_m: f64) -> String {
+    if usd_per_m == 0.0 { "free".into() } else { format!("${:.2}", usd_per_m) }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_code_generate(settings: &Settings, lang: &str, kind: &str, runtime: &RuntimeArgs, io: &IoArgs, http: &Client, code_only: bool, out_dir: &Option<String>) -> Result<()> {
+    let sel = select_provider(settings, runtime, "generate")?;
 
     let system = format!(
         "You are a senior software engineer. Create a minimal, runnable example in {} for a {}. Include clear comments and dependency instructions. If multiple files are required, consolidate into a single-file presentation.",
@@ -242,16 +1259,10 @@ pub async fn handle_code_generate(settings: &Settings, lang: &str, kind: &str, r
         ChatMessage { role: "user".into(), content: "Provide the implementation and a brief usage guide.".into() },
     ];
 
-    let pb = ProgressBar::new_spinner().with_message("Generating code...");
-    pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
-    pb.enable_steady_tick(std::time::Duration::from_millis(100));
-    let content = if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-        let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-        oa_chat(http, base, &api_key, messages, model).await?
-    } else {
-        or_chat(http, &api_key, messages, model).await?
-    };
-    pb.finish_and_clear();
+    let (resp, answered) = request_completion(&sel, http, sel.request(messages), false, "Generating code...").await?;
+    record_unsaved_usage(&answered.assistant_record(settings, http, &resp, chrono::Utc::now().timestamp_millis()).await);
+    if resp.interrupted { return Err(Interrupted.into()); }
+    let content = resp.content;
 
     // Post-process content
     if code_only || out_dir.is_some() {
@@ -286,89 +1297,37 @@ pub async fn handle_code_generate(settings: &Settings, lang: &str, kind: &str, r
 }
 
 pub async fn handle_code_review(settings: &Settings, file: &str, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<()> {
-    let api_key = settings
-        .api_key
-        .as_deref()
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("OPENROUTER_API_KEY").ok())
-        .ok_or_else(|| anyhow!("API key is not set. Use `config set api-key ...` or set env OPENROUTER_API_KEY"))?;
-    let api_key = crate::utils::secrets::normalize_api_key(&api_key);
-
-    let provider = runtime.provider.as_deref().unwrap_or(&settings.provider);
-    let model = runtime.model.as_deref().or(settings.model.as_deref());
+    let sel = select_provider(settings, runtime, "review")?;
     let code = crate::utils::io::read_to_string(file)?;
     let messages = vec![
         ChatMessage { role: "system".into(), content: "You are a rigorous and friendly code reviewer. Identify issues, risks, and improvements, and provide refactoring examples when necessary.".into() },
         ChatMessage { role: "user".into(), content: format!("Please review the following file {}:\n\n```\n{}\n```", file, code) },
     ];
-    let content = if runtime.stream {
-        if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-            let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-            oa_chat_stream(http, base, &api_key, messages, model, |chunk| { print!("{}", chunk); let _ = std::io::Write::flush(&mut std::io::stdout()); }).await?
-        } else {
-            or_chat_stream(http, &api_key, messages, model, |chunk| { print!("{}", chunk); let _ = std::io::Write::flush(&mut std::io::stdout()); }).await?
-        }
-    } else {
-        let pb = ProgressBar::new_spinner().with_message("Reviewing...");
-        pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
-        pb.enable_steady_tick(std::time::Duration::from_millis(100));
-        let r = if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-            let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-            oa_chat(http, base, &api_key, messages, model).await
-        } else {
-            or_chat(http, &api_key, messages, model).await
-        };
-        pb.finish_and_clear();
-        match r {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("{}", style(format!("Review failed: {}", e)).red());
-                return Err(e);
-            }
-        }
-    };
-    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &content)?; }
-    if !runtime.stream { println!("{}", content); }
+    let messages = sel.fit_context(messages, &[], Some(file));
+    let (resp, answered) = request_completion(&sel, http, sel.request(messages), runtime.stream, "Reviewing...")
+        .await
+        .context("Review failed")?;
+    record_unsaved_usage(&answered.assistant_record(settings, http, &resp, chrono::Utc::now().timestamp_millis()).await);
+    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &resp.content)?; }
+    if !runtime.stream { println!("{}", resp.content); }
+    if resp.interrupted { return Err(Interrupted.into()); }
     Ok(())
 }
 
 pub async fn handle_code_optimize(settings: &Settings, file: &str, runtime: &RuntimeArgs, io: &IoArgs, http: &Client) -> Result<()> {
-    let api_key = settings
-        .api_key
-        .as_deref()
-        .map(|s| s.to_string())
-        .or_else(|| std::env::var("OPENROUTER_API_KEY").ok())
-        .ok_or_else(|| anyhow!("API key is not set. Use `config set api-key ...` or set env OPENROUTER_API_KEY"))?;
-    let api_key = crate::utils::secrets::normalize_api_key(&api_key);
-
-    let provider = runtime.provider.as_deref().unwrap_or(&settings.provider);
-    let model = runtime.model.as_deref().or(settings.model.as_deref());
+    let sel = select_provider(settings, runtime, "optimize")?;
     let code = crate::utils::io::read_to_string(file)?;
     let messages = vec![
         ChatMessage { role: "system".into(), content: "You are a senior performance engineer. Optimize performance, readability, and error handling without changing semantics. Provide step-by-step suggestions and a final refactored version.".into() },
         ChatMessage { role: "user".into(), content: format!("Please optimize the following code {}:\n\n```\n{}\n```", file, code) },
     ];
-    let content = if runtime.stream {
-        if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-            let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-            oa_chat_stream(http, base, &api_key, messages, model, |chunk| { print!("{}", chunk); let _ = std::io::Write::flush(&mut std::io::stdout()); }).await?
-        } else {
-            or_chat_stream(http, &api_key, messages, model, |chunk| { print!("{}", chunk); let _ = std::io::Write::flush(&mut std::io::stdout()); }).await?
-        }
-    } else {
-        let pb = ProgressBar::new_spinner().with_message("Optimizing...");
-        pb.set_style(ProgressStyle::with_template("{spinner} {msg}").unwrap());
-        pb.enable_steady_tick(std::time::Duration::from_millis(100));
-        let r = if matches!(provider.to_lowercase().as_str(), "deepseek" | "qwen" | "openai" | "openai-compatible") {
-            let base = settings.base_url.as_deref().ok_or_else(|| anyhow!("Missing base_url in config for OpenAI-compatible provider"))?;
-            oa_chat(http, base, &api_key, messages, model).await
-        } else {
-            or_chat(http, &api_key, messages, model).await
-        };
-        pb.finish_and_clear();
-        match r { Ok(x) => x, Err(e) => { eprintln!("{}", style(format!("Optimize failed: {}", e)).red()); return Err(e); } }
-    };
-    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &content)?; }
-    if !runtime.stream { println!("{}", content); }
+    let messages = sel.fit_context(messages, &[], Some(file));
+    let (resp, answered) = request_completion(&sel, http, sel.request(messages), runtime.stream, "Optimizing...")
+        .await
+        .context("Optimize failed")?;
+    record_unsaved_usage(&answered.assistant_record(settings, http, &resp, chrono::Utc::now().timestamp_millis()).await);
+    if let Some(out) = &io.output_file { crate::utils::io::write_string(out, &resp.content)?; }
+    if !runtime.stream { println!("{}", resp.content); }
+    if resp.interrupted { return Err(Interrupted.into()); }
     Ok(())
 }
@@ -1 +1 @@
-pub mod settings;
\ No newline at end of file
+pub mod settings;
@@ -1,9 +1,19 @@
-use std::{fs, path::Path, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::Path, path::PathBuf};
 
 use anyhow::{Context, Result};
 use dirs::home_dir;
 use serde::{Deserialize, Serialize};
 
+use crate::api::cache::CacheSettings;
+use crate::api::client::HttpSettings;
+use crate::api::fallback::FallbackTarget;
+use crate::api::mock::MockOptions;
+use crate::api::models::SamplingParams;
+use crate::api::ollama::OllamaOptions;
+use crate::api::pricing::ModelPrice;
+use crate::api::retry::RetryPolicy;
+use crate::api::tokens::ContextSettings;
+
 const APP_DIR_NAME: &str = ".spark_cli";
 pub const CONFIG_FILE_NAME: &str = "config.toml";
 
@@ -11,10 +21,11 @@ pub const CONFIG_FILE_NAME: &str = "config.toml";
 #[serde(default)]
 pub struct Settings {
     pub provider: String,
+    /// Key for `provider`; other providers read their own env var
     pub api_key: Option<String>,
-    /// Preferred model for the active provider
+    /// Preferred model for `provider`
     pub model: Option<String>,
-    /// Base URL for OpenAI-compatible providers (DeepSeek/Qwen/OpenAI proxy)
+    /// Base URL for `provider`, required for OpenAI-compatible ones (DeepSeek/Qwen/OpenAI proxy)
     pub base_url: Option<String>,
     /// Automatically extract and write code blocks from responses
     pub auto_code_write: bool,
@@ -24,6 +35,38 @@ pub struct Settings {
     pub auto_code_multi_write: bool,
     /// Upper bound on blocks to write when multi-write is enabled
     pub max_auto_blocks: usize,
+    /// Options for the local Ollama provider
+    pub ollama: OllamaOptions,
+    /// Scripted replies for the offline `mock` provider
+    #[serde(skip_serializing_if = "MockOptions::is_default")]
+    pub mock: MockOptions,
+    /// Sampling defaults, globally and per command
+    pub sampling: SamplingSettings,
+    /// Per-model prices (USD per million tokens); take precedence over provider catalogs
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub pricing: BTreeMap<String, ModelPrice>,
+    /// Retry/backoff for transient provider failures (429, 5xx, network)
+    pub retry: RetryPolicy,
+    /// Timeouts, proxy, TLS roots and extra headers for provider requests
+    pub http: HttpSettings,
+    /// Opt-in on-disk cache of responses to identical requests
+    pub cache: CacheSettings,
+    /// Token budgeting against the model's context window
+    pub context: ContextSettings,
+    /// Backends tried in order when the provider fails (`[[fallback]]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackTarget>,
+}
+
+/// `[sampling]` applies to every request; `[sampling.commands.<name>]` overrides it
+/// for one command (`chat`, `generate`, `review`, `optimize`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingSettings {
+    #[serde(flatten)]
+    pub defaults: SamplingParams,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, SamplingParams>,
 }
 
 impl Default for Settings {
@@ -37,6 +80,15 @@ impl Default for Settings {
             output_dir: Some("generated".to_string()),
             auto_code_multi_write: false,
             max_auto_blocks: 10,
+            ollama: OllamaOptions::default(),
+            mock: MockOptions::default(),
+            sampling: SamplingSettings::default(),
+            pricing: BTreeMap::new(),
+            retry: RetryPolicy::default(),
+            http: HttpSettings::default(),
+            cache: CacheSettings::default(),
+            context: ContextSettings::default(),
+            fallback: Vec::new(),
         }
     }
 }
@@ -68,6 +120,20 @@ impl Settings {
         Ok(())
     }
 
+    /// Whether `name` is the configured `provider`, the one the top-level `api_key`,
+    /// `model` and `base_url` belong to.
+    pub fn is_configured_provider(&self, name: &str) -> bool {
+        name.trim().eq_ignore_ascii_case(self.provider.trim())
+    }
+
+    /// Sampling parameters for `command`: global defaults overlaid with the command's table.
+    pub fn sampling_for(&self, command: &str) -> SamplingParams {
+        match self.sampling.commands.get(command) {
+            Some(over) => self.sampling.defaults.merged(over),
+            None => self.sampling.defaults.clone(),
+        }
+    }
+
     pub fn load() -> Result<Self> { Self::load_with(None, None) }
     pub fn save(&self) -> Result<()> { self.save_with(None, None) }
 
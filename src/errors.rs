@@ -0,0 +1,270 @@
+use std::fmt;
+
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Context shared by every [`ProviderError`] variant.
+#[derive(Debug, Clone)]
+pub struct ErrorInfo {
+    /// Registry name of the provider that failed
+    pub provider: String,
+    pub status: Option<u16>,
+    /// Provider's own message, extracted from its error body when possible
+    pub message: String,
+}
+
+/// Classified provider failure. Built from HTTP error bodies (OpenAI, OpenRouter,
+/// Anthropic, Gemini and Ollama shapes) or transport errors, and surfaced to the
+/// user with a fix hint and a distinct exit code.
+#[derive(Debug, Clone)]
+pub enum ProviderError {
+    Auth(ErrorInfo),
+    RateLimited(ErrorInfo),
+    ContextLengthExceeded(ErrorInfo),
+    ModelNotFound(ErrorInfo),
+    InsufficientCredits(ErrorInfo),
+    ContentFiltered(ErrorInfo),
+    Timeout(ErrorInfo),
+    Network(ErrorInfo),
+    MalformedResponse(ErrorInfo),
+    /// Anything else, including 5xx responses
+    Other(ErrorInfo),
+}
+
+/// Coarse kind of a [`ProviderError`], as named in config (`on = ["rate_limited"]`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Auth,
+    RateLimited,
+    ContextLength,
+    ModelNotFound,
+    InsufficientCredits,
+    ContentFiltered,
+    Timeout,
+    Network,
+    Malformed,
+    /// 5xx responses
+    Server,
+    Other,
+}
+
+impl ProviderError {
+    pub fn info(&self) -> &ErrorInfo {
+        match self {
+            Self::Auth(i)
+            | Self::RateLimited(i)
+            | Self::ContextLengthExceeded(i)
+            | Self::ModelNotFound(i)
+            | Self::InsufficientCredits(i)
+            | Self::ContentFiltered(i)
+            | Self::Timeout(i)
+            | Self::Network(i)
+            | Self::MalformedResponse(i)
+            | Self::Other(i) => i,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Auth(_) => "Authentication failed",
+            Self::RateLimited(_) => "Rate limited",
+            Self::ContextLengthExceeded(_) => "Context length exceeded",
+            Self::ModelNotFound(_) => "Model not found",
+            Self::InsufficientCredits(_) => "Insufficient credits",
+            Self::ContentFiltered(_) => "Content filtered",
+            Self::Timeout(_) => "Request timed out",
+            Self::Network(_) => "Network error",
+            Self::MalformedResponse(_) => "Malformed response",
+            Self::Other(_) => "Provider error",
+        }
+    }
+
+    /// What the user can do about it.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::Auth(_) => Some("check your API key: run `spark config set api-key <KEY>` or set the provider's API key env var (e.g. OPENROUTER_API_KEY)"),
+            Self::RateLimited(_) => Some("wait and retry, raise `max_attempts` under [retry] in config, or switch with --model/--provider"),
+            Self::ContextLengthExceeded(_) => Some("shorten the input or start a new session, or try a model with a larger context window"),
+            Self::ModelNotFound(_) => Some("search available models with `spark models <query>` (add --refresh if the list is stale)"),
+            Self::InsufficientCredits(_) => Some("add credits or check billing for your provider account"),
+            Self::ContentFiltered(_) => Some("the provider's safety filter blocked this request; rephrase the prompt or try another model"),
+            Self::Timeout(_) => Some("retry, or use --stream so long responses arrive incrementally"),
+            Self::Network(_) => Some("check your network connection, proxy settings and `base_url`"),
+            Self::MalformedResponse(_) => Some("make sure `base_url` points at an API compatible with the selected provider"),
+            Self::Other(_) => None,
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::Auth(_) => ErrorClass::Auth,
+            Self::RateLimited(_) => ErrorClass::RateLimited,
+            Self::ContextLengthExceeded(_) => ErrorClass::ContextLength,
+            Self::ModelNotFound(_) => ErrorClass::ModelNotFound,
+            Self::InsufficientCredits(_) => ErrorClass::InsufficientCredits,
+            Self::ContentFiltered(_) => ErrorClass::ContentFiltered,
+            Self::Timeout(_) => ErrorClass::Timeout,
+            Self::Network(_) => ErrorClass::Network,
+            Self::MalformedResponse(_) => ErrorClass::Malformed,
+            Self::Other(i) if i.status.is_some_and(|s| s >= 500) => ErrorClass::Server,
+            Self::Other(_) => ErrorClass::Other,
+        }
+    }
+
+    /// Process exit code, distinct per class so scripts can branch on it.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Auth(_) => 10,
+            Self::RateLimited(_) => 11,
+            Self::ContextLengthExceeded(_) => 12,
+            Self::ModelNotFound(_) => 13,
+            Self::InsufficientCredits(_) => 14,
+            Self::ContentFiltered(_) => 15,
+            Self::Timeout(_) => 16,
+            Self::Network(_) => 17,
+            Self::MalformedResponse(_) => 18,
+            Self::Other(_) => 19,
+        }
+    }
+
+    /// Classify an HTTP error response from its status and body.
+    pub fn from_response(provider: &str, status: StatusCode, body: &str) -> Self {
+        let (message, code) = parse_error_body(body);
+        let message = message.unwrap_or_else(|| {
+            if body.trim().is_empty() { status.to_string() } else { body.trim().to_string() }
+        });
+        classify(ErrorInfo { provider: provider.to_string(), status: Some(status.as_u16()), message }, code.as_deref())
+    }
+
+    /// Classify an error object delivered inside a 200 response or stream.
+    pub fn from_body(provider: &str, body: &str) -> Self {
+        let (message, code) = parse_error_body(body);
+        let message = message.unwrap_or_else(|| body.trim().to_string());
+        classify(ErrorInfo { provider: provider.to_string(), status: None, message }, code.as_deref())
+    }
+
+    pub fn from_reqwest(provider: &str, e: reqwest::Error) -> Self {
+        let info = ErrorInfo { provider: provider.to_string(), status: e.status().map(|s| s.as_u16()), message: e.to_string() };
+        if e.is_timeout() {
+            Self::Timeout(info)
+        } else if e.is_decode() {
+            Self::MalformedResponse(info)
+        } else {
+            Self::Network(info)
+        }
+    }
+
+    pub fn malformed(provider: &str, message: impl Into<String>) -> Self {
+        Self::MalformedResponse(ErrorInfo { provider: provider.to_string(), status: None, message: message.into() })
+    }
+
+    pub fn content_filtered(provider: &str, message: impl Into<String>) -> Self {
+        Self::ContentFiltered(ErrorInfo { provider: provider.to_string(), status: None, message: message.into() })
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = self.info();
+        match info.status {
+            Some(status) => write!(f, "{} ({}, HTTP {}): {}", self.title(), info.provider, status, info.message),
+            None => write!(f, "{} ({}): {}", self.title(), info.provider, info.message),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// The user pressed Ctrl+C. Whatever arrived before that has already been saved.
+#[derive(Debug, Clone, Copy)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "Interrupted") }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// A streamed reply failed after part of it was printed. Sending the request again,
+/// to a fallback or otherwise, would print a second answer after the first one.
+#[derive(Debug, Clone, Copy)]
+pub struct PartialOutput;
+
+impl fmt::Display for PartialOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "Reply broke off after partial output") }
+}
+
+impl std::error::Error for PartialOutput {}
+
+/// Return the response if it succeeded, otherwise read its body into a [`ProviderError`].
+pub async fn check_status(provider: &str, resp: Response) -> Result<Response, ProviderError> {
+    if resp.status().is_success() { return Ok(resp); }
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    Err(ProviderError::from_response(provider, status, &text))
+}
+
+/// Pull `(message, code/type)` out of the error shapes providers use:
+/// `{"error": {"message", "code"|"type"|"status"}}`, `{"error": "..."}` or `{"message": ...}`.
+fn parse_error_body(body: &str) -> (Option<String>, Option<String>) {
+    let Ok(value) = serde_json::from_str::<Value>(body) else { return (None, None) };
+    let as_text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    match value.get("error") {
+        Some(Value::String(s)) => (Some(s.clone()), None),
+        Some(err) => {
+            let mut message = err.get("message").and_then(as_text);
+            // OpenRouter puts the upstream provider's raw error under metadata
+            if let Some(raw) = err.get("metadata").and_then(|m| m.get("raw")).and_then(as_text) {
+                message = Some(match message {
+                    Some(m) => format!("{} ({})", m, raw),
+                    None => raw,
+                });
+            }
+            let code = ["code", "type", "status"].iter().find_map(|k| err.get(*k).and_then(as_text));
+            (message, code)
+        }
+        None => (value.get("message").and_then(as_text), None),
+    }
+}
+
+fn classify(info: ErrorInfo, code: Option<&str>) -> ProviderError {
+    let code = code.unwrap_or("").to_lowercase();
+    let msg = info.message.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|n| code.contains(n) || msg.contains(n));
+
+    if has(&["context_length", "context length", "maximum context", "prompt is too long", "too many tokens", "token limit"]) {
+        return ProviderError::ContextLengthExceeded(info);
+    }
+    if has(&["insufficient_quota", "insufficient credits", "insufficient balance", "credit balance", "requires more credits", "billing"]) {
+        return ProviderError::InsufficientCredits(info);
+    }
+    if has(&["content_filter", "moderation", "flagged", "safety"]) {
+        return ProviderError::ContentFiltered(info);
+    }
+    if has(&["model_not_found", "no such model", "unknown model", "invalid model", "is not a valid model"])
+        || (msg.contains("model") && (msg.contains("not found") || msg.contains("does not exist")))
+    {
+        return ProviderError::ModelNotFound(info);
+    }
+    match info.status {
+        Some(401) | Some(403) => return ProviderError::Auth(info),
+        Some(402) => return ProviderError::InsufficientCredits(info),
+        Some(404) => return ProviderError::ModelNotFound(info),
+        Some(408) | Some(504) => return ProviderError::Timeout(info),
+        Some(429) => return ProviderError::RateLimited(info),
+        _ => {}
+    }
+    if has(&["authentication", "invalid_api_key", "invalid api key", "api key not valid", "unauthenticated", "permission_denied", "unauthorized"]) {
+        return ProviderError::Auth(info);
+    }
+    if has(&["rate_limit", "rate limit", "resource_exhausted"]) {
+        return ProviderError::RateLimited(info);
+    }
+    ProviderError::Other(info)
+}
@@ -1 +1,6 @@
+pub mod api;
+pub mod cli;
+pub mod config;
+pub mod errors;
+pub mod session;
 pub mod utils;
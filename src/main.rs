@@ -1,30 +1,50 @@
-mod cli;
-mod api;
-mod config;
-mod session;
-mod utils;
-
 use anyhow::Result;
 use clap::{Parser, CommandFactory};
-use cli::args::{Cli, CodeAction, Commands, ConfigAction, SessionAction};
+use console::style;
+use spark_cli::{cli, config};
+use spark_cli::api::cassette;
+use spark_cli::api::catalog::ModelFilter;
+use spark_cli::errors::{Interrupted, ProviderError};
+use spark_cli::session::search::SearchFilter;
+use cli::args::{CacheAction, Cli, CodeAction, Commands, ConfigAction, SessionAction, TagAction};
 use config::settings::Settings;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     init_tracing();
 
     let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        std::process::exit(report_error(&e));
+    }
+}
+
+/// Print the error chain (plus a fix hint for provider errors) and pick the exit code.
+fn report_error(e: &anyhow::Error) -> i32 {
+    // Conventional 128 + SIGINT; the partial reply was already reported
+    if e.downcast_ref::<Interrupted>().is_some() { return 130; }
+    eprintln!("{} {:#}", style("Error:").red().bold(), e);
+    match e.downcast_ref::<ProviderError>() {
+        Some(pe) => {
+            if let Some(hint) = pe.hint() { eprintln!("{} {}", style("hint:").cyan(), hint); }
+            pe.exit_code()
+        }
+        None => 1,
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     // Resolve explicit config path if provided
     let explicit_path = cli.runtime.config.as_deref().map(std::path::Path::new);
     // Project root: current working dir for project-scoped config
     let project_root = std::env::current_dir().ok();
     let mut settings = Settings::load_with(project_root.as_deref(), explicit_path)?;
 
-    // Shared HTTP client with timeout
-    let http = reqwest::Client::builder()
-        .pool_max_idle_per_host(8)
-        .timeout(std::time::Duration::from_secs(60))
-        .build()?;
+    // Shared HTTP client: timeouts, proxy and TLS roots from [http]
+    let http = settings.http.build_client()?;
+
+    // SPARK_CASSETTE: record provider traffic to, or replay it from, a cassette file
+    let _cassette = cassette::from_env(&mut settings, cli.runtime.provider.as_deref(), &http).await?;
 
     match &cli.command {
         Some(Commands::Interactive) => {
@@ -61,14 +81,68 @@ async fn main() -> Result<()> {
             SessionAction::New { name } => {
                 cli::commands::handle_session_new(&settings, name).await?
             }
-            SessionAction::List => cli::commands::handle_session_list(&settings).await?,
+            SessionAction::List { tag } => cli::commands::handle_session_list(&settings, tag).await?,
             SessionAction::Load { id } => {
                 cli::commands::handle_session_load(&settings, id).await?
             }
             SessionAction::Delete { id } => {
                 cli::commands::handle_session_delete(&settings, id).await?
             }
+            SessionAction::Stats { id } => {
+                cli::commands::handle_session_stats(&settings, id.as_deref()).await?
+            }
+            SessionAction::Show { id } => {
+                cli::commands::handle_session_show(&settings, id.as_deref()).await?
+            }
+            SessionAction::Export { id, format } => {
+                cli::commands::handle_session_export(&settings, id.as_deref(), format, &cli.io).await?
+            }
+            SessionAction::Pin { number } => {
+                cli::commands::handle_session_pin(&settings, *number, true).await?
+            }
+            SessionAction::Unpin { number } => {
+                cli::commands::handle_session_pin(&settings, *number, false).await?
+            }
+            SessionAction::Rename { name, id } => {
+                cli::commands::handle_session_rename(&settings, name, id.as_deref()).await?
+            }
+            SessionAction::Tag { action } => match action {
+                TagAction::Add { tags, id } => cli::commands::handle_session_tag(&settings, tags, true, id.as_deref()).await?,
+                TagAction::Remove { tags, id } => cli::commands::handle_session_tag(&settings, tags, false, id.as_deref()).await?,
+            },
+            SessionAction::Set { key, value, id } => {
+                cli::commands::handle_session_set(&settings, key, value.as_deref(), id.as_deref()).await?
+            }
+            SessionAction::Fork { id, at, name } => {
+                cli::commands::handle_session_fork(&settings, id, *at, name.as_deref()).await?
+            }
+            SessionAction::Tree => cli::commands::handle_session_tree(&settings).await?,
+            SessionAction::Search { query, regex, case_sensitive, role, since, until, limit, load } => {
+                let filter = SearchFilter { role: role.clone(), since: since.clone(), until: until.clone() };
+                cli::commands::handle_session_search(&settings, query, *regex, *case_sensitive, &filter, *limit, *load).await?
+            }
+            SessionAction::Import { file, from, name } => {
+                cli::commands::handle_session_import(&settings, file, from, name.as_deref()).await?
+            }
+        },
+        Some(Commands::Models { query, min_context, max_price, refresh }) => {
+            let filter = ModelFilter { query: query.clone(), min_context: *min_context, max_price: *max_price };
+            cli::commands::handle_models(&settings, &cli.runtime, &http, &filter, *refresh).await?
+        }
+        Some(Commands::Compare { models, prompt, out_dir, save }) => {
+            let prompt = if prompt.is_empty() { None } else { Some(prompt.join(" ")) };
+            cli::commands::handle_compare(&settings, models, prompt, &cli.runtime, &cli.io, &http, out_dir.as_deref(), *save).await?
+        }
+        Some(Commands::Batch { input, concurrency, retry_errors }) => {
+            cli::commands::handle_batch(&settings, input, *concurrency, *retry_errors, &cli.runtime, &cli.io, &http).await?
+        }
+        Some(Commands::Cache { action }) => match action {
+            CacheAction::Stats => cli::commands::handle_cache_stats(&settings).await?,
+            CacheAction::Clear => cli::commands::handle_cache_clear(&settings).await?,
         },
+        Some(Commands::Usage { by, since }) => {
+            cli::commands::handle_usage(&settings, by, since.as_deref()).await?
+        }
         Some(Commands::Code { action }) => match action {
             CodeAction::Generate { lang, r#type, code_only, out_dir } => {
                 cli::commands::handle_code_generate(&settings, lang, r#type, &cli.runtime, &cli.io, &http, *code_only, out_dir).await?
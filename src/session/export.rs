@@ -0,0 +1,290 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use chrono::TimeZone;
+use regex::Regex;
+use serde::Serialize;
+
+use super::history::MessageRecord;
+use super::manager::SessionMeta;
+use super::usage::UsageTotals;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            "json" => Some(Self::Json),
+            "jsonl" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// A whole session as written by `--format json`, and the first line of `--format jsonl`.
+#[derive(Debug, Serialize)]
+struct Document<'a> {
+    session: &'a SessionMeta,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    messages: Option<&'a [MessageRecord]>,
+}
+
+/// Render a session transcript. Timestamps are UTC so exports read the same everywhere.
+pub fn render(meta: &SessionMeta, records: &[MessageRecord], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => markdown(meta, records),
+        ExportFormat::Html => html(meta, records),
+        ExportFormat::Json => serde_json::to_string_pretty(&Document { session: meta, messages: Some(records) })? + "\n",
+        ExportFormat::Jsonl => {
+            let mut out = serde_json::to_string(&Document { session: meta, messages: None })?;
+            out.push('\n');
+            for record in records {
+                out.push_str(&serde_json::to_string(record)?);
+                out.push('\n');
+            }
+            out
+        }
+    })
+}
+
+/// `2026-01-02 03:04:05 UTC`
+pub fn utc_time(timestamp_ms: i64) -> String {
+    chrono::Utc
+        .timestamp_millis_opt(timestamp_ms)
+        .single()
+        .map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "unknown time".to_string())
+}
+
+/// `User`, `Assistant`, ...
+pub fn role_title(role: &str) -> String {
+    let mut chars = role.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// `openrouter / gpt-4o · 120 tokens · $0.0012 · interrupted` for an assistant message
+pub fn details(record: &MessageRecord) -> String {
+    let mut parts = Vec::new();
+    match (&record.provider, &record.model) {
+        (Some(p), Some(m)) => parts.push(format!("{} / {}", p, m)),
+        (Some(x), None) | (None, Some(x)) => parts.push(x.clone()),
+        (None, None) => {}
+    }
+    if let Some(u) = &record.usage { parts.push(format!("{} tokens", u.total_tokens)); }
+    if let Some(c) = record.cost_usd { parts.push(format!("${:.4}", c)); }
+    for (flag, label) in [(record.interrupted, "interrupted"), (record.cached, "cached"), (record.alternative, "alternative"), (record.pinned, "pinned")] {
+        if flag { parts.push(label.to_string()); }
+    }
+    parts.join(" · ")
+}
+
+/// Description, tags and the pinned provider, model and system prompt, where set.
+pub fn settings_lines(meta: &SessionMeta) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    if let Some(description) = &meta.description { lines.push(("Description".to_string(), description.clone())); }
+    if !meta.tags.is_empty() { lines.push(("Tags".to_string(), meta.tags.join(", "))); }
+    match (&meta.provider, &meta.model) {
+        (Some(provider), Some(model)) => lines.push(("Pinned model".to_string(), format!("{} / {}", provider, model))),
+        (Some(provider), None) => lines.push(("Pinned provider".to_string(), provider.clone())),
+        (None, Some(model)) => lines.push(("Pinned model".to_string(), model.clone())),
+        (None, None) => {}
+    }
+    if let Some(system) = &meta.system { lines.push(("System prompt".to_string(), system.replace('\n', " "))); }
+    lines
+}
+
+fn summary_lines(meta: &SessionMeta, records: &[MessageRecord]) -> Vec<(String, String)> {
+    let mut totals = UsageTotals::default();
+    records.iter().for_each(|r| totals.add(r));
+    let mut models: Vec<&str> = records.iter().filter_map(|r| r.model.as_deref()).collect();
+    models.sort();
+    models.dedup();
+    let mut lines = vec![
+        ("Session".to_string(), meta.id.clone()),
+        ("Created".to_string(), utc_time(meta.created_ms)),
+        ("Messages".to_string(), records.len().to_string()),
+    ];
+    if meta.updated_ms > meta.created_ms { lines.push(("Updated".to_string(), utc_time(meta.updated_ms))); }
+    if let (Some(parent), Some(at)) = (&meta.parent, meta.fork_at) {
+        lines.push(("Forked from".to_string(), format!("{} at message {}", parent, at)));
+    }
+    lines.extend(settings_lines(meta));
+    if !models.is_empty() { lines.push(("Models".to_string(), models.join(", "))); }
+    if totals.requests > 0 {
+        lines.push(("Tokens".to_string(), format!("{} ({} prompt, {} completion)", totals.total_tokens, totals.prompt_tokens, totals.completion_tokens)));
+        lines.push(("Cost".to_string(), format!("${:.4}{}", totals.cost_usd, if totals.unpriced > 0 { " (some requests unpriced)" } else { "" })));
+    }
+    lines
+}
+
+fn markdown(meta: &SessionMeta, records: &[MessageRecord]) -> String {
+    let mut out = format!("# {}\n\n", meta.name);
+    for (key, value) in summary_lines(meta, records) {
+        let _ = writeln!(out, "- **{}:** {}", key, value);
+    }
+    for record in records {
+        let _ = write!(out, "\n---\n\n### {} · {}\n", role_title(&record.role), utc_time(record.timestamp_ms));
+        let details = details(record);
+        if !details.is_empty() { let _ = writeln!(out, "\n*{}*", details); }
+        // Content is already Markdown; fences pass through untouched
+        let _ = writeln!(out, "\n{}", record.content.trim_end());
+    }
+    out
+}
+
+/// Text and fenced code blocks of a message, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Code { lang: &'a str, body: &'a str },
+}
+
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let re = Regex::new(r"(?s)```[ \t]*([^\n`]*)\n(.*?)```").expect("valid regex");
+    let mut out = Vec::new();
+    let mut last = 0;
+    for caps in re.captures_iter(text) {
+        let whole = caps.get(0).expect("match");
+        if whole.start() > last { out.push(Segment::Text(&text[last..whole.start()])); }
+        let lang = caps.get(1).map(|m| m.as_str().trim()).unwrap_or("");
+        out.push(Segment::Code { lang, body: caps.get(2).map(|m| m.as_str()).unwrap_or("") });
+        last = whole.end();
+    }
+    if last < text.len() { out.push(Segment::Text(&text[last..])); }
+    out
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "
+body { font: 15px/1.55 -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; max-width: 860px; margin: 2em auto; padding: 0 1em; color: #1f2328; }
+h1 { margin-bottom: .3em; }
+dl.meta { display: grid; grid-template-columns: max-content 1fr; gap: .2em 1em; color: #59636e; }
+dl.meta dt { font-weight: 600; }
+dl.meta dd { margin: 0; }
+section.msg { border: 1px solid #d1d9e0; border-radius: 8px; margin: 1.2em 0; padding: .2em 1em .6em; }
+section.user { background: #f6f8fa; }
+section.system { background: #fff8c5; }
+section.alternative { border-style: dashed; }
+header { display: flex; flex-wrap: wrap; gap: .8em; align-items: baseline; margin: .6em 0 .2em; }
+header .role { font-weight: 700; }
+header .time, header .details { color: #59636e; font-size: .85em; }
+pre { background: #0d1117; color: #e6edf3; padding: .8em 1em; border-radius: 6px; overflow-x: auto; font-size: 13px; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+p code { background: #eff1f3; padding: .1em .3em; border-radius: 4px; }
+.lang { float: right; color: #8b949e; font-size: 11px; }
+.kw { color: #ff7b72; } .str { color: #a5d6ff; } .com { color: #8b949e; font-style: italic; } .num { color: #79c0ff; }
+";
+
+fn html(meta: &SessionMeta, records: &[MessageRecord]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", escape_html(&meta.name), HTML_STYLE);
+    let _ = writeln!(out, "<h1>{}</h1>\n<dl class=\"meta\">", escape_html(&meta.name));
+    for (key, value) in summary_lines(meta, records) {
+        let _ = writeln!(out, "<dt>{}</dt><dd>{}</dd>", key, escape_html(&value));
+    }
+    out.push_str("</dl>\n");
+    for record in records {
+        let class = if record.alternative { format!("{} alternative", record.role) } else { record.role.clone() };
+        let _ = writeln!(out, "<section class=\"msg {}\">", escape_html(&class));
+        let _ = write!(out, "<header><span class=\"role\">{}</span><span class=\"time\">{}</span>", escape_html(&role_title(&record.role)), utc_time(record.timestamp_ms));
+        let details = details(record);
+        if !details.is_empty() { let _ = write!(out, "<span class=\"details\">{}</span>", escape_html(&details)); }
+        out.push_str("</header>\n");
+        for segment in segments(&record.content) {
+            match segment {
+                Segment::Text(text) => out.push_str(&text_html(text)),
+                Segment::Code { lang, body } => {
+                    let label = if lang.is_empty() { String::new() } else { format!("<span class=\"lang\">{}</span>", escape_html(lang)) };
+                    let _ = writeln!(out, "<pre>{}<code>{}</code></pre>", label, highlight(lang, body));
+                }
+            }
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Paragraphs split on blank lines, with `inline code` and line breaks kept.
+fn text_html(text: &str) -> String {
+    let inline = Regex::new(r"`([^`\n]+)`").expect("valid regex");
+    let mut out = String::new();
+    for para in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let escaped = escape_html(para);
+        let with_code = inline.replace_all(&escaped, "<code>$1</code>");
+        let _ = writeln!(out, "<p>{}</p>", with_code.replace('\n', "<br>\n"));
+    }
+    out
+}
+
+const KEYWORDS: &[&str] = &[
+    // Rust
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+    // Python, JavaScript/TypeScript, Go, Java, C family, shell
+    "and", "class", "def", "del", "elif", "except", "finally", "from", "global", "import", "is", "lambda", "None", "nonlocal", "not", "or",
+    "pass", "raise", "True", "False", "try", "with", "yield", "function", "var", "new", "this", "typeof", "instanceof", "export", "default",
+    "interface", "extends", "implements", "null", "undefined", "package", "func", "go", "defer", "chan", "select", "case", "switch", "public",
+    "private", "protected", "void", "int", "char", "float", "double", "long", "short", "bool", "boolean", "throw", "throws", "catch", "do",
+    "then", "fi", "esac", "done", "echo",
+];
+
+/// Keyword, string, comment and number highlighting that works well enough across
+/// common languages without a grammar per language.
+pub fn highlight(lang: &str, code: &str) -> String {
+    let lang = lang.to_ascii_lowercase();
+    let hash_comments = matches!(lang.as_str(), "python" | "py" | "sh" | "bash" | "shell" | "zsh" | "ruby" | "rb" | "toml" | "yaml" | "yml" | "dockerfile" | "makefile");
+    let comment = if hash_comments { r"#[^\n]*" } else { r"//[^\n]*|/\*[\s\S]*?\*/" };
+    // Rust lifetimes ('a) look like quotes; only single-character literals are strings there
+    let single = if matches!(lang.as_str(), "rust" | "rs") { r"'(?:\\.|[^'\\\n])'" } else { r"'(?:\\.|[^'\\\n])*'" };
+    let pattern = format!(r#"(?P<com>{})|(?P<str>"(?:\\.|[^"\\\n])*"|{}|`[^`]*`)|(?P<num>\b\d[\d_]*(?:\.\d+)?\b)|(?P<word>\b[A-Za-z_][A-Za-z0-9_]*\b)"#, comment, single);
+    let re = Regex::new(&pattern).expect("valid regex");
+    let mut out = String::with_capacity(code.len() * 2);
+    let mut last = 0;
+    for caps in re.captures_iter(code) {
+        let whole = caps.get(0).expect("match");
+        out.push_str(&escape_html(&code[last..whole.start()]));
+        let text = escape_html(whole.as_str());
+        let class = if caps.name("com").is_some() {
+            Some("com")
+        } else if caps.name("str").is_some() {
+            Some("str")
+        } else if caps.name("num").is_some() {
+            Some("num")
+        } else if KEYWORDS.contains(&whole.as_str()) {
+            Some("kw")
+        } else {
+            None
+        };
+        match class {
+            Some(c) => { let _ = write!(out, "<span class=\"{}\">{}</span>", c, text); }
+            None => out.push_str(&text),
+        }
+        last = whole.end();
+    }
+    out.push_str(&escape_html(&code[last..]));
+    out
+}
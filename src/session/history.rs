@@ -1,8 +1,39 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::api::models::{ChatMessage, Usage};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MessageRecord {
     pub role: String,
     pub content: String,
     pub timestamp_ms: i64,
+    /// Provider that produced an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Estimated spend in USD, when the model's price is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// Assistant reply stopped with Ctrl+C before it finished
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// Replayed from the response cache; no tokens were spent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Another model's answer to the same prompt (`compare --save`); kept for
+    /// reference but not sent back as conversation context
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alternative: bool,
+    /// Always sent as context, even when older turns are dropped to fit the model
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+}
+
+impl From<&MessageRecord> for ChatMessage {
+    fn from(record: &MessageRecord) -> Self {
+        ChatMessage { role: record.role.clone(), content: record.content.clone() }
+    }
 }
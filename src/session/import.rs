@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::history::MessageRecord;
+use super::manager::SessionMeta;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// `conversations.json` from a ChatGPT data export
+    ChatGpt,
+    /// An OpenAI chat `messages` array, bare or as `{"messages": [...]}`
+    OpenAiMessages,
+    /// `session export --format jsonl` output, or a bare `history.jsonl`
+    SparkJsonl,
+}
+
+impl ImportSource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "chatgpt" => Some(Self::ChatGpt),
+            "openai-messages" => Some(Self::OpenAiMessages),
+            "spark-jsonl" => Some(Self::SparkJsonl),
+            _ => None,
+        }
+    }
+}
+
+/// A conversation converted to spark records, ready for `SessionManager::import_session`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedSession {
+    /// Name, creation time and, from spark exports, tags and settings; the id is assigned on import
+    pub meta: SessionMeta,
+    pub records: Vec<MessageRecord>,
+    /// What was left out, by kind (e.g. `image part`, `tool message`)
+    pub skipped: BTreeMap<String, usize>,
+}
+
+impl ImportedSession {
+    fn skip(&mut self, kind: impl Into<String>) {
+        *self.skipped.entry(kind.into()).or_default() += 1;
+    }
+}
+
+/// Convert `content` from `source`. `name` is used for formats without titles, and
+/// `now_ms` for messages without timestamps.
+pub fn parse(source: ImportSource, content: &str, name: &str, now_ms: i64) -> Result<Vec<ImportedSession>> {
+    match source {
+        ImportSource::ChatGpt => chatgpt(content, name, now_ms),
+        ImportSource::OpenAiMessages => openai_messages(content, name, now_ms).map(|s| vec![s]),
+        ImportSource::SparkJsonl => spark_jsonl(content, name, now_ms).map(|s| vec![s]),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptConversation {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, ChatGptNode>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptNode {
+    #[serde(default)]
+    message: Option<ChatGptMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    content: Value,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+fn secs_to_ms(secs: f64) -> i64 { (secs * 1000.0).round() as i64 }
+
+/// ChatGPT stores each conversation as a tree of edits and regenerations; the branch
+/// ending at `current_node` is the one that was on screen, so only that is imported.
+fn chatgpt(content: &str, name: &str, now_ms: i64) -> Result<Vec<ImportedSession>> {
+    let conversations: Vec<ChatGptConversation> = match serde_json::from_str(content) {
+        Ok(list) => list,
+        // A single conversation object instead of the export's array
+        Err(_) => vec![serde_json::from_str(content).context("Not a ChatGPT conversations.json export")?],
+    };
+    let mut sessions = Vec::new();
+    for conv in conversations {
+        let title = conv.title.clone().filter(|t| !t.trim().is_empty()).unwrap_or_else(|| name.to_string());
+        let mut session = ImportedSession { meta: SessionMeta { name: title, ..Default::default() }, ..Default::default() };
+        let mut branch = Vec::new();
+        let mut cursor = conv.current_node.clone();
+        while let Some(id) = cursor {
+            let Some(node) = conv.mapping.get(&id) else { break };
+            if branch.len() > conv.mapping.len() { return Err(anyhow!("ChatGPT conversation '{}' has a cycle", session.meta.name)); }
+            branch.push(node);
+            cursor = node.parent.clone();
+        }
+        branch.reverse();
+        // Without its own time, the conversation started with its first dated message
+        let first_ms = branch.iter().find_map(|n| n.message.as_ref().and_then(|m| m.create_time));
+        session.meta.created_ms = conv.create_time.or(first_ms).map(secs_to_ms).unwrap_or(now_ms);
+        let mut last_ms = session.meta.created_ms;
+        for message in branch.into_iter().filter_map(|n| n.message.as_ref()) {
+            let role = message.author.role.as_str();
+            if !matches!(role, "user" | "assistant" | "system") {
+                session.skip(format!("{} message", role));
+                continue;
+            }
+            let Some(text) = chatgpt_text(&message.content, &mut session) else { continue };
+            if text.trim().is_empty() { continue; }
+            last_ms = message.create_time.map(secs_to_ms).unwrap_or(last_ms);
+            let model = message.metadata.get("model_slug").and_then(Value::as_str).map(str::to_string);
+            session.records.push(MessageRecord {
+                role: role.to_string(),
+                content: text,
+                timestamp_ms: last_ms,
+                provider: (role == "assistant").then(|| "chatgpt".to_string()),
+                model: model.filter(|_| role == "assistant"),
+                ..Default::default()
+            });
+        }
+        sessions.push(session);
+    }
+    Ok(sessions)
+}
+
+/// Text of a ChatGPT message; other content types and non-text parts are counted as skipped.
+fn chatgpt_text(content: &Value, session: &mut ImportedSession) -> Option<String> {
+    let kind = content.get("content_type").and_then(Value::as_str).unwrap_or("text");
+    match kind {
+        "text" | "multimodal_text" => {
+            let parts = content.get("parts").and_then(Value::as_array)?;
+            let mut texts = Vec::new();
+            for part in parts {
+                match part {
+                    Value::String(s) => texts.push(s.clone()),
+                    other => {
+                        let kind = other.get("content_type").and_then(Value::as_str).unwrap_or("non-text");
+                        session.skip(format!("{} part", kind.trim_end_matches("_asset_pointer")));
+                    }
+                }
+            }
+            Some(texts.join("\n"))
+        }
+        "code" => {
+            let text = content.get("text").and_then(Value::as_str)?;
+            let lang = content.get("language").and_then(Value::as_str).filter(|l| *l != "unknown").unwrap_or("");
+            Some(format!("```{}\n{}\n```", lang, text.trim_end()))
+        }
+        other => {
+            session.skip(format!("{} content", other));
+            None
+        }
+    }
+}
+
+fn openai_messages(content: &str, name: &str, now_ms: i64) -> Result<ImportedSession> {
+    let value: Value = serde_json::from_str(content).context("Not a JSON file")?;
+    let messages = match &value {
+        Value::Array(list) => list,
+        Value::Object(map) => map.get("messages").and_then(Value::as_array).ok_or_else(|| anyhow!("Expected a `messages` array"))?,
+        _ => return Err(anyhow!("Expected a `messages` array")),
+    };
+    let meta = SessionMeta { name: name.to_string(), created_ms: now_ms, ..Default::default() };
+    let mut session = ImportedSession { meta, ..Default::default() };
+    for message in messages {
+        let role = message.get("role").and_then(Value::as_str).ok_or_else(|| anyhow!("Message without a role: {}", message))?;
+        // `developer` is the newer name for system instructions
+        let role = if role == "developer" { "system" } else { role };
+        if !matches!(role, "user" | "assistant" | "system") {
+            session.skip(format!("{} message", role));
+            continue;
+        }
+        let text = match message.get("content") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(parts)) => {
+                let mut texts = Vec::new();
+                for part in parts {
+                    match (part.get("type").and_then(Value::as_str), part.get("text").and_then(Value::as_str)) {
+                        (Some("text"), Some(text)) => texts.push(text.to_string()),
+                        (kind, _) => session.skip(format!("{} part", kind.unwrap_or("unknown"))),
+                    }
+                }
+                texts.join("\n")
+            }
+            _ => String::new(),
+        };
+        if message.get("tool_calls").is_some() { session.skip("tool call"); }
+        if text.trim().is_empty() { continue; }
+        session.records.push(MessageRecord { role: role.to_string(), content: text, timestamp_ms: now_ms, ..Default::default() });
+    }
+    Ok(session)
+}
+
+#[derive(Deserialize)]
+struct SparkHeader {
+    session: SessionMeta,
+}
+
+fn spark_jsonl(content: &str, name: &str, now_ms: i64) -> Result<ImportedSession> {
+    let mut session = ImportedSession { meta: SessionMeta { name: name.to_string(), ..Default::default() }, ..Default::default() };
+    let mut header = None;
+    let mut first = true;
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        // The export header is the first non-blank line
+        if std::mem::take(&mut first) {
+            if let Ok(parsed) = serde_json::from_str::<SparkHeader>(line) {
+                header = Some(parsed.session);
+                continue;
+            }
+        }
+        let record: MessageRecord = serde_json::from_str(line).with_context(|| format!("Invalid history line {}", idx + 1))?;
+        session.records.push(record);
+    }
+    match header {
+        // Everything the export recorded carries over: tags, description, settings and lineage
+        Some(meta) => session.meta = meta,
+        // A bare history.jsonl has no header; the session started with its first message
+        None => session.meta.created_ms = session.records.first().map(|r| r.timestamp_ms).unwrap_or(now_ms),
+    }
+    Ok(session)
+}
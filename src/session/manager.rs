@@ -1,6 +1,6 @@
 use std::{fs, io::Write, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use dirs::home_dir;
 use serde::{Deserialize, Serialize};
 
@@ -10,17 +10,48 @@ const APP_DIR_NAME: &str = ".spark_cli";
 const SESSIONS_DIR: &str = "sessions";
 const CURRENT_FILE: &str = "CURRENT";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SessionMeta {
     pub id: String,
     pub name: String,
     pub created_ms: i64,
+    /// Session this one was forked from (`session fork`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Number of the parent's messages copied into the fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_at: Option<usize>,
+    /// Last message or metadata change; 0 in sessions from older versions
+    #[serde(default)]
+    pub updated_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Provider used for this session's chats unless `--provider` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model used for this session's chats unless `--model` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Sent as the first message of every chat in this session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+}
+
+impl SessionMeta {
+    /// When the session last changed, falling back to its creation for older sessions.
+    pub fn last_active_ms(&self) -> i64 { self.updated_ms.max(self.created_ms) }
 }
 
 pub struct SessionManager {
     root: PathBuf,
 }
 
+impl Default for SessionManager {
+    fn default() -> Self { Self::new() }
+}
+
 impl SessionManager {
     pub fn new() -> Self {
         let root = home_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -28,22 +59,103 @@ impl SessionManager {
         Self { root }
     }
 
+    /// Manager rooted at an explicit sessions directory instead of `~/.spark_cli/sessions`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
     fn now_ms() -> i64 {
         SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64
     }
 
+    /// An unused id: the current time in ms, stepped past any that exist.
+    fn new_id(&self) -> String {
+        let mut n = Self::now_ms();
+        while self.root.join(n.to_string()).exists() { n += 1; }
+        n.to_string()
+    }
+
     pub fn create_session(&self, name: &str) -> Result<String> {
-        let id = format!("{}", Self::now_ms());
+        let id = self.new_id();
         let dir = self.root.join(&id);
         fs::create_dir_all(&dir)?;
         // save meta
-        let meta = SessionMeta { id: id.clone(), name: name.to_string(), created_ms: Self::now_ms() };
+        let now = Self::now_ms();
+        let meta = SessionMeta { id: id.clone(), name: name.to_string(), created_ms: now, updated_ms: now, ..Default::default() };
         fs::write(dir.join("meta.json"), serde_json::to_vec_pretty(&meta)?)?;
         // init history file
         fs::write(dir.join("history.jsonl"), b"")?;
         Ok(id)
     }
 
+    /// Create a session from records made elsewhere under a new id, keeping the
+    /// timestamps, tags and settings in `meta` and `records`.
+    pub fn import_session(&self, meta: SessionMeta, records: &[MessageRecord]) -> Result<String> {
+        self.create_with_history(meta, records)
+    }
+
+    /// New session holding the first `at` messages of `id`; the parent is left as is.
+    pub fn fork_session(&self, id: &str, at: usize, name: Option<&str>) -> Result<SessionMeta> {
+        let parent = self.load_meta(id)?;
+        let mut records = self.load_history(id)?;
+        if at == 0 || at > records.len() {
+            return Err(anyhow!("Session {} has no message {} ({} message(s))", id, at, records.len()));
+        }
+        records.truncate(at);
+        // The parent already accounts for this spend; copies would count it twice
+        for r in &mut records {
+            r.usage = None;
+            r.cost_usd = None;
+        }
+        let meta = SessionMeta {
+            name: name.map(str::to_string).unwrap_or_else(|| format!("{} (fork)", parent.name)),
+            created_ms: Self::now_ms(),
+            parent: Some(parent.id.clone()),
+            fork_at: Some(at),
+            // Settings carry over; the fork is a new place to try things, not a new topic
+            description: parent.description,
+            tags: parent.tags,
+            provider: parent.provider,
+            model: parent.model,
+            system: parent.system,
+            ..Default::default()
+        };
+        let id = self.create_with_history(meta.clone(), &records)?;
+        Ok(SessionMeta { id, ..meta })
+    }
+
+    fn create_with_history(&self, meta: SessionMeta, records: &[MessageRecord]) -> Result<String> {
+        let id = self.new_id();
+        fs::create_dir_all(self.root.join(&id))?;
+        let updated_ms = records.last().map(|r| r.timestamp_ms).unwrap_or(meta.created_ms).max(meta.created_ms).max(meta.updated_ms);
+        self.write_history(&id, records)?;
+        self.save_meta(&SessionMeta { id: id.clone(), updated_ms, ..meta })?;
+        Ok(id)
+    }
+
+    pub fn load_meta(&self, id: &str) -> Result<SessionMeta> {
+        let path = self.root.join(id).join("meta.json");
+        let bytes = fs::read(&path).map_err(|_| anyhow!("Session {} not found", id))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("read session meta failed: {}", path.display()))
+    }
+
+    fn save_meta(&self, meta: &SessionMeta) -> Result<()> {
+        let path = self.root.join(&meta.id).join("meta.json");
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(meta)?).with_context(|| format!("write session meta failed: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Apply `change` to a session's metadata and bump `updated_ms`.
+    pub fn update_meta(&self, id: &str, change: impl FnOnce(&mut SessionMeta) -> Result<()>) -> Result<SessionMeta> {
+        let mut meta = self.load_meta(id)?;
+        change(&mut meta)?;
+        meta.updated_ms = Self::now_ms();
+        self.save_meta(&meta)?;
+        Ok(meta)
+    }
+
     pub fn list_sessions(&self) -> Result<Vec<SessionMeta>> {
         let mut results = Vec::new();
         if !self.root.exists() { return Ok(results); }
@@ -60,8 +172,8 @@ impl SessionManager {
                 }
             }
         }
-        // sort by created_ms desc
-        results.sort_by(|a, b| b.created_ms.cmp(&a.created_ms));
+        // most recently active first
+        results.sort_by_key(|m| std::cmp::Reverse(m.last_active_ms()));
         Ok(results)
     }
 
@@ -72,9 +184,66 @@ impl SessionManager {
         fs::OpenOptions::new().create(true).append(true).open(&path)
             .with_context(|| format!("open history failed: {}", path.display()))?
             .write_all(line.as_bytes())?;
+        // Sessions without metadata still take messages
+        if let Ok(mut meta) = self.load_meta(id) {
+            meta.updated_ms = meta.updated_ms.max(record.timestamp_ms);
+            self.save_meta(&meta)?;
+        }
         Ok(())
     }
 
+    /// Read every record in a session's `history.jsonl`, oldest first.
+    /// Lines that fail to parse are skipped with a warning.
+    pub fn load_history(&self, id: &str) -> Result<Vec<MessageRecord>> {
+        let path = self.root.join(id).join("history.jsonl");
+        if !path.exists() { return Ok(Vec::new()); }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("read history failed: {}", path.display()))?;
+        let mut records = Vec::new();
+        for (idx, line) in content.lines().enumerate() {
+            if line.trim().is_empty() { continue; }
+            match serde_json::from_str::<MessageRecord>(line) {
+                Ok(r) => records.push(r),
+                Err(e) => tracing::warn!("skipping malformed history line {} in {}: {}", idx + 1, path.display(), e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// The records replayed as conversation context: alternative answers from
+    /// `compare` are left out.
+    pub fn load_context(&self, id: &str) -> Result<Vec<MessageRecord>> {
+        Ok(self.load_history(id)?.into_iter().filter(|r| !r.alternative).collect())
+    }
+
+    /// Replace a session's `history.jsonl` with `records`.
+    pub fn write_history(&self, id: &str, records: &[MessageRecord]) -> Result<()> {
+        let path = self.root.join(id).join("history.jsonl");
+        let mut content = String::new();
+        for record in records {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, content).with_context(|| format!("write history failed: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Pin or unpin message `number` (1-based, as stored in `history.jsonl`).
+    pub fn set_pinned(&self, id: &str, number: usize, pinned: bool) -> Result<MessageRecord> {
+        let mut records = self.load_history(id)?;
+        let len = records.len();
+        let record = number
+            .checked_sub(1)
+            .and_then(|i| records.get_mut(i))
+            .ok_or_else(|| anyhow!("Session {} has no message {} ({} message(s))", id, number, len))?;
+        record.pinned = pinned;
+        let record = record.clone();
+        self.write_history(id, &records)?;
+        Ok(record)
+    }
+
     pub fn delete_session(&self, id: &str) -> Result<()> {
         let dir = self.root.join(id);
         if dir.exists() { fs::remove_dir_all(dir)?; }
@@ -1,2 +1,6 @@
 pub mod manager;
 pub mod history;
+pub mod usage;
+pub mod export;
+pub mod import;
+pub mod search;
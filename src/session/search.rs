@@ -0,0 +1,78 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexBuilder};
+
+use super::history::MessageRecord;
+use super::manager::{SessionManager, SessionMeta};
+use super::usage::day_of;
+
+/// Which messages `search` looks at. Dates are local `YYYY-MM-DD` days, both inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub role: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+impl SearchFilter {
+    pub fn matches(&self, record: &MessageRecord) -> bool {
+        if self.role.as_deref().is_some_and(|r| r != record.role) { return false; }
+        if self.since.is_none() && self.until.is_none() { return true; }
+        // ISO dates compare correctly as strings
+        let day = day_of(record.timestamp_ms);
+        self.since.as_deref().is_none_or(|d| day.as_str() >= d) && self.until.as_deref().is_none_or(|d| day.as_str() <= d)
+    }
+}
+
+/// A message containing the pattern; `range` is the first match in its content.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub session: SessionMeta,
+    /// 1-based, as in `session show`
+    pub number: usize,
+    pub record: MessageRecord,
+    pub range: Range<usize>,
+}
+
+/// `query` as a regex (or a literal unless `regex`), case-insensitive unless `case_sensitive`.
+pub fn pattern(query: &str, regex: bool, case_sensitive: bool) -> Result<Regex> {
+    let source = if regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&source)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| anyhow!("Invalid search pattern: {}", e))
+}
+
+/// Every matching message across all sessions, newest first.
+pub fn search(mgr: &SessionManager, pattern: &Regex, filter: &SearchFilter) -> Result<Vec<Hit>> {
+    let mut hits = Vec::new();
+    for session in mgr.list_sessions()? {
+        for (idx, record) in mgr.load_history(&session.id)?.into_iter().enumerate() {
+            if !filter.matches(&record) { continue; }
+            let Some(m) = pattern.find(&record.content) else { continue };
+            hits.push(Hit { session: session.clone(), number: idx + 1, range: m.range(), record });
+        }
+    }
+    hits.sort_by_key(|h| std::cmp::Reverse(h.record.timestamp_ms));
+    Ok(hits)
+}
+
+/// The match with up to `width` characters of context on each side, on one line:
+/// `(before, matched, after)`, with `…` where the content was cut.
+pub fn snippet(content: &str, range: Range<usize>, width: usize) -> (String, String, String) {
+    let line = |s: &str| s.replace(['\r', '\n', '\t'], " ");
+    let before: Vec<char> = line(&content[..range.start]).chars().collect();
+    let after: Vec<char> = line(&content[range.end..]).chars().collect();
+    let head = if before.len() > width {
+        format!("…{}", before[before.len() - width..].iter().collect::<String>())
+    } else {
+        before.iter().collect::<String>().trim_start().to_string()
+    };
+    let tail = if after.len() > width {
+        format!("{}…", after[..width].iter().collect::<String>())
+    } else {
+        after.iter().collect::<String>().trim_end().to_string()
+    };
+    (head, line(&content[range]), tail)
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fs, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::TimeZone;
+use dirs::home_dir;
+
+use super::history::MessageRecord;
+use super::manager::SessionMeta;
+
+/// Accumulated token counts and spend for one report row.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    /// Requests whose model had no known price
+    pub unpriced: u64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, record: &MessageRecord) {
+        let Some(u) = &record.usage else { return };
+        self.requests += 1;
+        self.prompt_tokens += u.prompt_tokens;
+        self.completion_tokens += u.completion_tokens;
+        self.total_tokens += u.total_tokens;
+        match record.cost_usd {
+            Some(c) => self.cost_usd += c,
+            None => self.unpriced += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced += other.unpriced;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Day,
+    Model,
+    Session,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "day" => Some(Self::Day),
+            "model" => Some(Self::Model),
+            "session" => Some(Self::Session),
+            _ => None,
+        }
+    }
+}
+
+/// Local calendar day (`YYYY-MM-DD`) of a millisecond timestamp.
+pub fn day_of(timestamp_ms: i64) -> String {
+    chrono::Local
+        .timestamp_millis_opt(timestamp_ms)
+        .single()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Row name for calls from the usage log, which belong to no session.
+pub const NO_SESSION: &str = "(no session)";
+
+/// Usage of replies that no session keeps: chat without a current session, the `code`
+/// commands, `compare` without `--save` and `batch`. One record per line in
+/// `~/.spark_cli/usage.jsonl`, without the reply text.
+pub struct UsageLog {
+    path: PathBuf,
+}
+
+impl Default for UsageLog {
+    fn default() -> Self { Self::new() }
+}
+
+impl UsageLog {
+    pub fn new() -> Self {
+        let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
+        Self { path: home.join(".spark_cli").join("usage.jsonl") }
+    }
+
+    /// Log at an explicit path instead of `~/.spark_cli/usage.jsonl`.
+    pub fn with_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Append `record` if it carries usage; its content is dropped.
+    pub fn append(&self, record: &MessageRecord) -> Result<()> {
+        if record.usage.is_none() { return Ok(()); }
+        if let Some(dir) = self.path.parent() { fs::create_dir_all(dir)?; }
+        let line = serde_json::to_string(&MessageRecord { content: String::new(), ..record.clone() })?;
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Every logged record; lines that fail to parse (e.g. torn by a crash) are skipped.
+    pub fn load(&self) -> Result<Vec<MessageRecord>> {
+        if !self.path.exists() { return Ok(Vec::new()); }
+        let content = fs::read_to_string(&self.path).with_context(|| format!("Failed to read {}", self.path.display()))?;
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+}
+
+/// Group records that carry usage by `group`. Records without usage are ignored;
+/// a meta with an empty id stands for the usage log.
+pub fn summarize<'a>(
+    entries: impl IntoIterator<Item = (&'a SessionMeta, &'a MessageRecord)>,
+    group: GroupBy,
+) -> BTreeMap<String, UsageTotals> {
+    let mut rows: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for (meta, record) in entries {
+        if record.usage.is_none() { continue; }
+        let key = match group {
+            GroupBy::Day => day_of(record.timestamp_ms),
+            GroupBy::Model => record.model.clone().unwrap_or_else(|| "unknown".to_string()),
+            GroupBy::Session if meta.id.is_empty() => NO_SESSION.to_string(),
+            GroupBy::Session => format!("{} ({})", meta.id, meta.name),
+        };
+        rows.entry(key).or_default().add(record);
+    }
+    rows
+}
@@ -51,7 +51,7 @@ pub fn choose_best_block<'a>(blocks: &'a [CodeBlock], preferred_langs: &[&str])
         }
     }
     // otherwise, first block
-    blocks.get(0)
+    blocks.first()
 }
 
 pub fn guess_ext_from_lang(lang: &str) -> &str {
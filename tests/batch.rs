@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use spark_cli::api::batch::{read_items, read_results};
+
+fn spark(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spark_cli"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env_remove("SPARK_CASSETTE")
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap()
+}
+
+fn results(path: &Path) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(path).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+}
+
+#[test]
+fn test_read_items_validates_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("in.jsonl");
+    std::fs::write(&path, "{\"id\": 7, \"prompt\": \"hi\", \"system\": \"terse\", \"params\": {\"temperature\": 0}}\n\n{\"id\": \"x\", \"prompt\": \"yo\"}\n").unwrap();
+    let items = read_items(&path).unwrap();
+    assert_eq!(items[0].id, "7");
+    assert_eq!(items[0].params.temperature, Some(0.0));
+    let roles: Vec<String> = items[0].messages().into_iter().map(|m| m.role).collect();
+    assert_eq!(roles, ["system", "user"]);
+    assert_eq!(items[1].messages().len(), 1);
+
+    std::fs::write(&path, "{\"id\": 1, \"prompt\": \"a\"}\n{\"id\": \"1\", \"prompt\": \"b\"}\n").unwrap();
+    assert!(read_items(&path).unwrap_err().to_string().contains("Duplicate id '1' on line 2"));
+    std::fs::write(&path, "{\"id\": 1, \"prompt\": \"a\"}\n{\"prompt\": \"b\"}\n").unwrap();
+    assert!(read_items(&path).unwrap_err().to_string().contains("line 2"));
+}
+
+#[test]
+fn test_read_results_ignores_torn_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.jsonl");
+    assert!(read_results(&path).unwrap().is_empty());
+    std::fs::write(&path, "{\"id\":\"1\",\"content\":\"ok\",\"latency_ms\":3}\n{\"id\":\"2\",\"cont").unwrap();
+    let done = read_results(&path).unwrap();
+    assert_eq!(done.len(), 1);
+    assert_eq!(done[0].content.as_deref(), Some("ok"));
+}
+
+#[test]
+fn test_batch_writes_results_and_resumes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("config.toml"), "provider = \"mock\"\n").unwrap();
+    let lines: Vec<String> = (1..=6).map(|i| format!("{{\"id\": {}, \"prompt\": \"prompt {}\"}}", i, i)).collect();
+    std::fs::write(dir.path().join("in.jsonl"), lines[..4].join("\n")).unwrap();
+
+    let out = spark(dir.path(), &["batch", "--input", "in.jsonl", "-o", "out.jsonl", "--concurrency", "3"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let first = results(&dir.path().join("out.jsonl"));
+    assert_eq!(first.len(), 4);
+    let one = first.iter().find(|r| r["id"] == "1").unwrap();
+    assert_eq!(one["content"], "prompt 1");
+    assert_eq!(one["model"], "mock");
+    assert!(one["usage"]["total_tokens"].as_u64().unwrap() > 0);
+
+    // Simulate an interrupted run: a torn line at the end, then more input
+    let mut raw = std::fs::read_to_string(dir.path().join("out.jsonl")).unwrap();
+    raw.push_str("{\"id\":\"5\",\"con");
+    std::fs::write(dir.path().join("out.jsonl"), raw).unwrap();
+    std::fs::write(dir.path().join("in.jsonl"), lines.join("\n")).unwrap();
+    let out = spark(dir.path(), &["batch", "--input", "in.jsonl", "-o", "out.jsonl"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Skipping 4 of 6"));
+    let mut ids: Vec<String> = results(&dir.path().join("out.jsonl")).iter().map(|r| r["id"].as_str().unwrap().to_string()).collect();
+    ids.sort();
+    assert_eq!(ids, ["1", "2", "3", "4", "5", "6"]);
+}
+
+#[test]
+fn test_batch_records_errors_and_retries_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = "provider = \"mock\"\n[retry]\nmax_attempts = 1\n[mock]\nfixture = \"script.toml\"\n";
+    std::fs::write(dir.path().join("config.toml"), config).unwrap();
+    std::fs::write(dir.path().join("script.toml"), "[[responses]]\nmatch = \"flaky\"\ncontent = \"\"\nstatus = 429\n\n[[responses]]\ncontent = \"fine\"\n").unwrap();
+    std::fs::write(dir.path().join("in.jsonl"), "{\"id\": \"a\", \"prompt\": \"steady\"}\n{\"id\": \"b\", \"prompt\": \"flaky\"}\n").unwrap();
+
+    let out = spark(dir.path(), &["batch", "--input", "in.jsonl", "-o", "out.jsonl"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("1 succeeded, 1 failed"));
+    let rows = results(&dir.path().join("out.jsonl"));
+    let failed = rows.iter().find(|r| r["id"] == "b").unwrap();
+    assert_eq!(failed["error_class"], "rate_limited");
+    assert!(failed.get("content").is_none());
+
+    std::fs::write(dir.path().join("script.toml"), "[[responses]]\ncontent = \"recovered\"\n").unwrap();
+    let out = spark(dir.path(), &["batch", "--input", "in.jsonl", "-o", "out.jsonl", "--retry-errors"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let rows = results(&dir.path().join("out.jsonl"));
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["content"], "fine");
+    assert_eq!(rows[1]["content"], "recovered");
+}
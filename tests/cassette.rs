@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use spark_cli::api::cassette::{Cassette, CassetteServer, CASSETTE_ENV, CASSETTE_MODE_ENV};
+use spark_cli::api::models::{ChatMessage, ChatRequest, SamplingParams};
+use spark_cli::api::providers::{ChatProvider, ProviderContext};
+use spark_cli::api::retry::RetryPolicy;
+use spark_cli::api::{anthropic, google, ollama, openai_compat, openrouter};
+use spark_cli::errors::ProviderError;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cassettes").join(name)
+}
+
+fn ctx<'a>(http: &'a reqwest::Client, base_url: &'a str) -> ProviderContext<'a> {
+    ProviderContext {
+        http,
+        api_key: Some("sk-test-secret"),
+        base_url: Some(base_url),
+        retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+        on_retry: None,
+        headers: None,
+    }
+}
+
+fn request(model: &str, prompt: &str) -> ChatRequest {
+    ChatRequest {
+        model: model.to_string(),
+        messages: vec![ChatMessage { role: "user".into(), content: prompt.into() }],
+        stream: None,
+        params: SamplingParams::default(),
+        stream_options: None,
+    }
+}
+
+#[tokio::test]
+async fn test_openrouter_completion_replay() {
+    let server = CassetteServer::replay(&fixture("openrouter_chat.json")).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = reqwest::Client::new();
+
+    let resp = openrouter::OpenRouter.complete(&ctx(&http, &base), request("", "Say hi")).await.unwrap();
+    assert_eq!(resp.content, "Hi! How can I help?");
+    assert_eq!(resp.model.as_deref(), Some("openai/gpt-4o-mini"));
+    assert_eq!(resp.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(resp.usage.unwrap().total_tokens, 15);
+
+    let sent = server.requests();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].path, "/api/v1/chat/completions");
+    assert_eq!(sent[0].headers["authorization"], "REDACTED");
+    let body = sent[0].body.as_ref().unwrap();
+    assert_eq!(body["model"], "openrouter/auto");
+    assert!(body.get("stream").is_none());
+    assert_eq!(server.unused(), 0);
+}
+
+#[tokio::test]
+async fn test_openrouter_stream_replay() {
+    let server = CassetteServer::replay(&fixture("openrouter_stream.json")).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = reqwest::Client::new();
+
+    let mut seen = Vec::new();
+    let mut on_chunk = |s: &str| seen.push(s.to_string());
+    let resp = openrouter::OpenRouter
+        .stream(&ctx(&http, &base), request("openai/gpt-4o-mini", "Say hi"), &mut on_chunk)
+        .await
+        .unwrap();
+    assert_eq!(seen, ["Hel", "lo, ", "wörld"]);
+    assert_eq!(resp.content, "Hello, wörld");
+    assert_eq!(resp.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(resp.usage.unwrap().completion_tokens, 4);
+
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"]["include_usage"], true);
+}
+
+#[tokio::test]
+async fn test_openrouter_error_replay_is_classified() {
+    let server = CassetteServer::replay(&fixture("openrouter_auth_error.json")).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = reqwest::Client::new();
+
+    let err = openrouter::OpenRouter.complete(&ctx(&http, &base), request("", "Say hi")).await.unwrap_err();
+    match err.downcast_ref::<ProviderError>() {
+        Some(ProviderError::Auth(info)) => {
+            assert_eq!(info.status, Some(401));
+            assert_eq!(info.message, "No auth credentials found");
+        }
+        other => panic!("expected auth error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_openrouter_models_replay() {
+    let server = CassetteServer::replay(&fixture("openrouter_models.json")).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = reqwest::Client::new();
+
+    let models = openrouter::OpenRouter.list_models(&ctx(&http, &base)).await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["openai/gpt-4o-mini", "anthropic/claude-sonnet-4"]);
+    assert_eq!(models[0].context_length, Some(128_000));
+    assert_eq!(models[0].modality().as_deref(), Some("text+image->text"));
+}
+
+#[tokio::test]
+async fn test_anthropic_models_follow_pages() {
+    let server = CassetteServer::replay(&fixture("anthropic_models_paged.json")).await.unwrap();
+    let base = server.rebase("https://api.anthropic.com/v1");
+    let http = reqwest::Client::new();
+
+    let models = anthropic::Anthropic.list_models(&ctx(&http, &base)).await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["claude-sonnet-4-20250514", "claude-3-5-haiku-20241022"]);
+    assert_eq!(server.unused(), 0);
+}
+
+#[tokio::test]
+async fn test_google_models_follow_page_tokens() {
+    let server = CassetteServer::replay(&fixture("google_models_paged.json")).await.unwrap();
+    let base = server.rebase("https://generativelanguage.googleapis.com/v1beta");
+    let http = reqwest::Client::new();
+
+    let models = google::Gemini.list_models(&ctx(&http, &base)).await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["gemini-2.0-flash", "gemini-2.5-pro"]);
+    assert_eq!(models[1].context_length, Some(1_048_576));
+    assert_eq!(server.unused(), 0);
+}
+
+#[tokio::test]
+async fn test_openai_compatible_stream_replay() {
+    let server = CassetteServer::replay(&fixture("openai_compat_stream.json")).await.unwrap();
+    let base = server.rebase("https://api.deepseek.com/v1");
+    let http = reqwest::Client::new();
+
+    let mut on_chunk = |_: &str| {};
+    let resp = openai_compat::OpenAiCompat
+        .stream(&ctx(&http, &base), request("deepseek-chat", "2+2?"), &mut on_chunk)
+        .await
+        .unwrap();
+    assert_eq!(resp.content, "2 + 2 = 4");
+    assert_eq!(resp.model.as_deref(), Some("deepseek-chat"));
+    assert_eq!(resp.usage.unwrap().total_tokens, 17);
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_replay() {
+    let server = CassetteServer::replay(&fixture("anthropic_stream.json")).await.unwrap();
+    let base = server.rebase("https://api.anthropic.com/v1");
+    let http = reqwest::Client::new();
+
+    let mut on_chunk = |_: &str| {};
+    let resp = anthropic::Anthropic
+        .stream(&ctx(&http, &base), request("", "Say hi"), &mut on_chunk)
+        .await
+        .unwrap();
+    assert_eq!(resp.content, "Hello there");
+    assert_eq!(resp.finish_reason.as_deref(), Some("end_turn"));
+    let usage = resp.usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (11, 3));
+    assert_eq!(server.requests()[0].headers["x-api-key"], "REDACTED");
+}
+
+#[tokio::test]
+async fn test_ollama_stream_keeps_final_line_without_newline() {
+    let server = CassetteServer::replay(&fixture("ollama_stream.json")).await.unwrap();
+    let base = server.rebase("http://localhost:11434");
+    let http = reqwest::Client::new();
+
+    let mut on_chunk = |_: &str| {};
+    let resp = ollama::Ollama::default()
+        .stream(&ctx(&http, &base), request("", "Say hi"), &mut on_chunk)
+        .await
+        .unwrap();
+    assert_eq!(resp.content, "Hello there");
+    assert_eq!(resp.finish_reason.as_deref(), Some("stop"));
+    let usage = resp.usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (26, 3));
+}
+
+#[tokio::test]
+async fn test_unrecorded_request_fails_without_retry() {
+    let server = CassetteServer::replay(&fixture("openrouter_models.json")).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = reqwest::Client::new();
+
+    let err = openrouter::OpenRouter.complete(&ctx(&http, &base), request("", "Say hi")).await.unwrap_err();
+    assert!(err.to_string().contains("no recorded interaction"), "{}", err);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_record_redacts_credentials_and_keeps_stream_bytes() {
+    // A replay server stands in for the real API so recording runs offline
+    let upstream = CassetteServer::replay(&fixture("openrouter_stream.json")).await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recorded.json");
+    let http = reqwest::Client::new();
+    let recorder = CassetteServer::record(&path, &upstream.rebase("https://openrouter.ai/api/v1"), http.clone()).await.unwrap();
+    let base = recorder.rebase("https://openrouter.ai/api/v1");
+
+    let mut on_chunk = |_: &str| {};
+    let resp = openrouter::OpenRouter
+        .stream(&ctx(&http, &base), request("openai/gpt-4o-mini", "Say hi"), &mut on_chunk)
+        .await
+        .unwrap();
+    assert_eq!(resp.content, "Hello, wörld");
+    assert_eq!(upstream.requests().len(), 1);
+
+    let recorded = Cassette::load(&path).unwrap();
+    assert_eq!(recorded.interactions.len(), 1);
+    let interaction = &recorded.interactions[0];
+    assert_eq!(interaction.request.headers["authorization"], "REDACTED");
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("sk-test-secret"));
+    assert_eq!(interaction.response.status, 200);
+    assert_eq!(interaction.response.headers["content-type"], "text/event-stream");
+
+    let original = Cassette::load(&fixture("openrouter_stream.json")).unwrap();
+    assert_eq!(interaction.response.chunks.concat(), original.interactions[0].response.chunks.concat());
+}
+
+#[test]
+fn test_cli_replays_cassette_from_env() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("config.toml"), "provider = \"openrouter\"\nauto_code_write = false\n").unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_spark_cli"))
+        .arg("Say hi")
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .env("OPENROUTER_API_KEY", "sk-test-secret")
+        .env(CASSETTE_ENV, fixture("openrouter_chat.json"))
+        .env_remove(CASSETTE_MODE_ENV)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Hi! How can I help?"));
+}
@@ -0,0 +1,59 @@
+use spark_cli::api::catalog::{contains_model, suggest, ModelFilter};
+use spark_cli::api::models::ModelInfo;
+
+fn catalog() -> Vec<ModelInfo> {
+    // Shape of OpenRouter's /api/v1/models entries
+    serde_json::from_str(r#"[
+        {"id":"openai/gpt-4o","name":"OpenAI: GPT-4o","context_length":128000,
+         "pricing":{"prompt":"0.0000025","completion":"0.00001"},
+         "architecture":{"modality":"text+image->text","input_modalities":["text","image"],"output_modalities":["text"]}},
+        {"id":"openai/gpt-4o-mini","name":"OpenAI: GPT-4o-mini","context_length":128000,
+         "pricing":{"prompt":"0.00000015","completion":"0.0000006"}},
+        {"id":"deepseek/deepseek-chat","name":"DeepSeek V3","context_length":64000,
+         "pricing":{"prompt":"0","completion":"0"},"architecture":{"modality":"text->text"}},
+        {"id":"llama3.2:latest"}
+    ]"#).unwrap()
+}
+
+fn ids(models: &[ModelInfo], filter: &ModelFilter) -> Vec<String> {
+    models.iter().filter(|m| filter.matches(m)).map(|m| m.id.clone()).collect()
+}
+
+#[test]
+fn test_filters_by_query_context_and_price() {
+    let models = catalog();
+    let by_name = ModelFilter { query: Some("deepseek v3".into()), ..Default::default() };
+    assert_eq!(ids(&models, &by_name), ["deepseek/deepseek-chat"]);
+
+    let big = ModelFilter { min_context: Some(100_000), ..Default::default() };
+    assert_eq!(ids(&models, &big), ["openai/gpt-4o", "openai/gpt-4o-mini"]);
+
+    // USD per million prompt tokens; unpriced models are excluded
+    let cheap = ModelFilter { max_price: Some(1.0), ..Default::default() };
+    assert_eq!(ids(&models, &cheap), ["openai/gpt-4o-mini", "deepseek/deepseek-chat"]);
+}
+
+#[test]
+fn test_modality_prefers_explicit_lists() {
+    let models = catalog();
+    assert_eq!(models[0].modality().as_deref(), Some("text+image->text"));
+    assert_eq!(models[2].modality().as_deref(), Some("text->text"));
+    assert_eq!(models[3].modality(), None);
+}
+
+#[test]
+fn test_contains_model_accepts_ollama_latest_tag() {
+    let models = catalog();
+    assert!(contains_model(&models, "openai/gpt-4o"));
+    assert!(contains_model(&models, "llama3.2"));
+    assert!(!contains_model(&models, "gpt-4o"));
+}
+
+#[test]
+fn test_suggestions_for_near_misses() {
+    let models = catalog();
+    assert_eq!(suggest(&models, "gpt-4o", 2), ["openai/gpt-4o", "openai/gpt-4o-mini"]);
+    assert_eq!(suggest(&models, "openai/gpt4o", 1), ["openai/gpt-4o"]);
+    assert_eq!(suggest(&models, "deepseek-chta", 3), ["deepseek/deepseek-chat"]);
+    assert!(suggest(&models, "claude-opus", 3).is_empty());
+}
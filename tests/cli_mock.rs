@@ -0,0 +1,405 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+struct Sandbox {
+    dir: tempfile::TempDir,
+}
+
+impl Sandbox {
+    /// A working directory whose `config.toml` selects the mock provider, plus `extra` config.
+    fn new(extra: &str) -> Self {
+        Self::with_config(&format!("provider = \"mock\"\n{}", extra))
+    }
+
+    fn with_config(config: &str) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config.toml"), config).unwrap();
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path { self.dir.path() }
+
+    fn write(&self, name: &str, content: &str) {
+        std::fs::write(self.path().join(name), content).unwrap();
+    }
+
+    fn run(&self, args: &[&str]) -> Output { self.run_env(args, &[]) }
+
+    /// Run with `vars` set; provider key variables from the outer environment are removed.
+    fn run_env(&self, args: &[&str], vars: &[(&str, &str)]) -> Output {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_spark_cli"));
+        cmd.args(args).current_dir(self.path()).env("HOME", self.path()).env_remove("RUST_LOG");
+        for var in ["OPENROUTER_API_KEY", "ANTHROPIC_API_KEY", "GEMINI_API_KEY", "OPENAI_API_KEY"] { cmd.env_remove(var); }
+        cmd.envs(vars.iter().copied()).output().unwrap()
+    }
+
+    fn run_ok(&self, args: &[&str]) -> String {
+        let out = self.run(args);
+        assert!(out.status.success(), "{:?} failed: {}", args, String::from_utf8_lossy(&out.stderr));
+        String::from_utf8(out.stdout).unwrap()
+    }
+
+    fn session_dir(&self) -> PathBuf {
+        let sessions = self.path().join(".spark_cli").join("sessions");
+        std::fs::read_dir(&sessions).unwrap().map(|e| e.unwrap().path()).find(|p| p.is_dir()).unwrap()
+    }
+
+    fn history(&self) -> Vec<serde_json::Value> {
+        let raw = std::fs::read_to_string(self.session_dir().join("history.jsonl")).unwrap();
+        raw.lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    fn history_of(&self, id: &str) -> Vec<serde_json::Value> {
+        let raw = std::fs::read_to_string(self.path().join(".spark_cli/sessions").join(id).join("history.jsonl")).unwrap();
+        raw.lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+}
+
+#[test]
+fn test_echo_chat_is_recorded_in_session() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.run_ok(&["session", "new", "demo"]);
+    assert_eq!(sb.run_ok(&["hello there"]), "hello there\n");
+
+    let history = sb.history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["role"], "user");
+    assert_eq!(history[1]["content"], "hello there");
+    assert_eq!(history[1]["provider"], "mock");
+    assert_eq!(history[1]["model"], "mock");
+    assert!(history[1]["usage"]["total_tokens"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_streamed_chunks_reassemble() {
+    let sb = Sandbox::new("auto_code_write = false\n[mock]\nchunk_size = 3\nlatency_ms = 1\n");
+    assert_eq!(sb.run_ok(&["--stream", "a streamed reply"]), "a streamed reply\n");
+}
+
+#[test]
+fn test_code_mode_auto_writes_snippet() {
+    let sb = Sandbox::new("[mock]\nmode = \"code\"\n");
+    sb.run_ok(&["write me a program"]);
+    let snippet = std::fs::read_to_string(sb.path().join("generated").join("snippet.rs")).unwrap();
+    assert!(snippet.contains("Hello from the spark mock provider"));
+}
+
+#[test]
+fn test_code_generate_code_only() {
+    let sb = Sandbox::new("[mock]\nmode = \"code\"\n");
+    let out = sb.run_ok(&["code", "generate", "--lang", "rust", "--type", "cli", "--code-only"]);
+    assert!(out.starts_with("fn main() {"));
+    assert!(!out.contains("```"));
+}
+
+#[test]
+fn test_script_fixture_by_turn_and_match() {
+    let sb = Sandbox::new("auto_code_write = false\n[mock]\nfixture = \"script.toml\"\n");
+    sb.write(
+        "script.toml",
+        r#"
+[[responses]]
+match = "review the following"
+content = "Looks good to me."
+
+[[responses]]
+turn = 1
+content = "first answer"
+
+[[responses]]
+turn = 2
+content = "second answer"
+"#,
+    );
+    sb.run_ok(&["session", "new", "scripted"]);
+    assert_eq!(sb.run_ok(&["one"]), "first answer\n");
+    assert_eq!(sb.run_ok(&["two"]), "second answer\n");
+
+    sb.write("main.rs", "fn main() {}\n");
+    sb.run_ok(&["code", "review", "main.rs", "-o", "review.md"]);
+    assert_eq!(std::fs::read_to_string(sb.path().join("review.md")).unwrap(), "Looks good to me.");
+}
+
+#[test]
+fn test_simulated_status_maps_to_exit_code() {
+    let sb = Sandbox::new("[mock]\nerror_status = 429\n");
+    let out = sb.run(&["hi"]);
+    assert_eq!(out.status.code(), Some(11));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("Rate limited (mock, HTTP 429)"));
+    assert!(stderr.contains("hint:"));
+}
+
+#[test]
+fn test_jsonl_fixture_error_body() {
+    let sb = Sandbox::new("[mock]\nfixture = \"script.jsonl\"\n");
+    sb.write("script.jsonl", r#"{"status": 401, "body": "{\"error\":{\"message\":\"No auth credentials found\",\"code\":401}}"}"#);
+    let out = sb.run(&["hi"]);
+    assert_eq!(out.status.code(), Some(10));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("No auth credentials found"));
+}
+
+#[test]
+fn test_response_cache_hit_refresh_and_bypass() {
+    let sb = Sandbox::new("auto_code_write = false\n[mock]\nfixture = \"script.toml\"\n[cache]\nenabled = true\n");
+    sb.write("script.toml", "[[responses]]\ncontent = \"v1\"\n");
+    assert_eq!(sb.run_ok(&["question"]), "v1\n");
+
+    // A changed answer is only seen when the cache is bypassed or refreshed
+    sb.write("script.toml", "[[responses]]\ncontent = \"v2\"\n");
+    let out = sb.run(&["question"]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "v1\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Using cached response"));
+    assert_eq!(sb.run_ok(&["--no-cache", "question"]), "v2\n");
+    assert_eq!(sb.run_ok(&["question"]), "v1\n");
+    assert_eq!(sb.run_ok(&["--refresh", "question"]), "v2\n");
+    assert_eq!(sb.run_ok(&["question"]), "v2\n");
+    // Different sampling params are a different request
+    assert_eq!(sb.run_ok(&["--temperature", "0", "question"]), "v2\n");
+    sb.write("script.toml", "[[responses]]\ncontent = \"v3\"\n");
+    assert_eq!(sb.run_ok(&["--temperature", "0.5", "question"]), "v3\n");
+}
+
+#[test]
+fn test_cached_reply_streams_and_cache_commands() {
+    let sb = Sandbox::new("auto_code_write = false\n[cache]\nenabled = true\n");
+    assert_eq!(sb.run_ok(&["--stream", "line one\nline two"]), "line one\nline two\n");
+    // A fresh session sends the same messages, so the reply is replayed from the cache
+    sb.run_ok(&["session", "new", "cached"]);
+    assert_eq!(sb.run_ok(&["--stream", "line one\nline two"]), "line one\nline two\n");
+
+    // The replayed reply is marked and carries no spend
+    let history = sb.history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1]["cached"], true);
+    assert!(history[1].get("usage").is_none());
+
+    let stats = sb.run_ok(&["cache", "stats"]);
+    assert!(stats.contains("enabled") && stats.contains("Entries:  1 (0 expired)"), "{}", stats);
+    assert_eq!(sb.run_ok(&["cache", "clear"]), "Removed 1 cached response(s)\n");
+    assert!(sb.run_ok(&["cache", "stats"]).contains("Entries:  0"));
+}
+
+#[test]
+fn test_history_is_trimmed_to_context_and_pins_stay() {
+    let sb = Sandbox::new("auto_code_write = false\n[context]\ncontext_length = 80\nreserve_tokens = 0\n[mock]\nfixture = \"script.toml\"\n");
+    sb.write("script.toml", "[[responses]]\nturn = 1\ncontent = \"one turn\"\n\n[[responses]]\ncontent = \"more turns\"\n");
+    sb.run_ok(&["session", "new", "long"]);
+    let prompt = "lorem ".repeat(40);
+    assert_eq!(sb.run_ok(&[&prompt]), "one turn\n");
+
+    // The first exchange no longer fits next to a second long prompt
+    let out = sb.run(&[&prompt]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "one turn\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Left out 2 older message(s)"));
+
+    // A pinned message is kept even though the request then no longer fits
+    assert!(sb.run_ok(&["session", "pin", "1"]).starts_with("Pinned message 1 (user)"));
+    let out = sb.run(&[&prompt]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "more turns\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("the provider will likely reject it"));
+    assert_eq!(sb.history()[0]["pinned"], true);
+}
+
+#[test]
+fn test_review_warns_about_oversized_file() {
+    let sb = Sandbox::new("[context]\ncontext_length = 100\n");
+    sb.write("big.rs", &"let x = 1;\n".repeat(200));
+    let out = sb.run(&["code", "review", "big.rs"]);
+    assert!(out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("big.rs is ~"), "{}", stderr);
+}
+
+#[test]
+fn test_session_show_and_export() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.run_ok(&["session", "new", "transcript"]);
+    sb.run_ok(&["first question"]);
+    let shown = sb.run_ok(&["session", "show"]);
+    assert!(shown.starts_with("transcript ("));
+    assert!(shown.contains("#1 User"));
+    assert!(shown.contains("#2 Assistant"));
+    assert!(shown.contains("mock / mock"));
+
+    sb.run_ok(&["session", "export", "--format", "html", "-o", "out/transcript.html"]);
+    let html = std::fs::read_to_string(sb.path().join("out/transcript.html")).unwrap();
+    assert!(html.contains("<h1>transcript</h1>") && html.contains("<p>first question</p>"));
+    let md = sb.run_ok(&["session", "export"]);
+    assert!(md.starts_with("# transcript\n"));
+
+    let out = sb.run(&["session", "export", "missing-id"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Session missing-id not found"));
+}
+
+#[test]
+fn test_session_import_reports_skipped_content() {
+    let sb = Sandbox::new("");
+    let fixture = std::fs::read_to_string("tests/fixtures/import/chatgpt_conversations.json").unwrap();
+    sb.write("conversations.json", &fixture);
+    let out = sb.run_ok(&["session", "import", "conversations.json", "--from", "chatgpt"]);
+    assert!(out.contains("Borrow checker help (4 message(s))"));
+    assert!(out.contains("Imported 1 session(s)"));
+    assert!(out.contains("Left out 1 conversation(s) with no text messages"));
+    assert!(out.contains("Skipped non-text content: 1 image part(s), 1 tool message(s)"));
+    let listed = sb.run_ok(&["session", "list"]);
+    assert!(listed.contains("Borrow checker help"));
+}
+
+#[test]
+fn test_session_fork_and_tree() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.run_ok(&["session", "new", "main"]);
+    sb.run_ok(&["first question"]);
+    sb.run_ok(&["second question"]);
+    let main_id = sb.run_ok(&["session", "list"]).split_whitespace().nth(1).unwrap().to_string();
+    let out = sb.run_ok(&["session", "fork", &main_id, "--at", "2", "--name", "alt"]);
+    assert!(out.starts_with(&format!("Forked {} at message 2 -> ", main_id)));
+    sb.run_ok(&["a different second question"]);
+    sb.run_ok(&["session", "fork", &main_id, "--at", "1"]);
+
+    let tree = sb.run_ok(&["session", "tree"]);
+    let lines: Vec<&str> = tree.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", tree);
+    assert!(lines[0].contains(&format!("{} - main (4 message(s))", main_id)));
+    assert!(lines[1].contains("├─ ") && lines[1].contains("- alt (at #2, 4 message(s))"));
+    assert!(lines[2].starts_with("*") && lines[2].contains("└─ ") && lines[2].contains("- main (fork) (at #1, 1 message(s))"));
+
+    let out = sb.run(&["session", "fork", &main_id, "--at", "9"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("has no message 9 (4 message(s))"));
+}
+
+#[test]
+fn test_session_search_and_load() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.run_ok(&["session", "new", "tokio notes"]);
+    sb.run_ok(&["how does tokio select work"]);
+    sb.run_ok(&["session", "new", "other"]);
+    sb.run_ok(&["unrelated"]);
+
+    let out = sb.run_ok(&["session", "search", "TOKIO", "--role", "user", "--load"]);
+    assert!(out.contains("tokio notes #1 User"), "{}", out);
+    assert!(out.contains("how does tokio select work"));
+    assert!(out.contains("1 match(es) in 1 session(s)"));
+    assert!(out.contains("Switched to session"));
+    assert!(sb.run_ok(&["session", "list"]).lines().any(|l| l.starts_with('*') && l.ends_with("tokio notes")));
+
+    assert!(sb.run_ok(&["session", "search", "nothing like this"]).contains("No matches"));
+    let out = sb.run(&["session", "search", "x", "--since", "last week"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Dates must be YYYY-MM-DD"));
+}
+
+#[test]
+fn test_session_metadata_commands_apply_to_chat() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.run_ok(&["session", "new", "draft"]);
+    assert!(sb.run_ok(&["session", "rename", "tokio notes"]).contains("draft -> tokio notes"));
+    assert!(sb.run_ok(&["session", "tag", "add", "rust", "async", "rust"]).contains("#rust #async"));
+    assert!(sb.run_ok(&["session", "tag", "remove", "async"]).contains("Tags of"));
+    assert!(!sb.run(&["session", "tag", "add", "two words"]).status.success());
+    sb.run_ok(&["session", "new", "untagged"]);
+    let tagged = sb.run_ok(&["session", "list", "--tag", "rust"]);
+    assert_eq!(tagged.lines().count(), 1);
+    assert!(tagged.contains("tokio notes #rust"));
+    let id = tagged.split_whitespace().next().unwrap().to_string();
+    sb.run_ok(&["session", "load", &id]);
+    sb.run_ok(&["session", "set", "description", "Migration notes"]);
+    let listed = sb.run_ok(&["session", "list"]);
+    assert!(listed.lines().next().unwrap().ends_with("tokio notes #rust"), "{}", listed);
+    assert_eq!(listed.lines().nth(1).unwrap().trim(), "Migration notes");
+
+    // The mock counts a token per 4 characters of every message, system prompt included
+    sb.run_ok(&["session", "set", "system", "You are terse. You answer in one line.."]);
+    sb.run_ok(&["hi"]);
+    let history = sb.history_of(&id);
+    assert_eq!(history.len(), 2, "the system prompt is not stored as a message");
+    assert_eq!(history[1]["usage"]["prompt_tokens"], 11);
+    assert!(sb.run_ok(&["session", "show"]).contains("System prompt: You are terse."));
+
+    sb.run_ok(&["session", "set", "provider", "nosuch"]);
+    assert!(!sb.run(&["hi"]).status.success());
+    assert_eq!(sb.run_ok(&["--provider", "mock", "hi"]), "hi\n");
+    assert!(sb.run_ok(&["session", "set", "provider"]).contains("Cleared provider"));
+    sb.run_ok(&["hi"]);
+}
+
+#[test]
+fn test_provider_flag_leaves_configured_credentials_behind() {
+    let sb = Sandbox::with_config(
+        "provider = \"openrouter\"\napi_key = \"sk-or-secret\"\nmodel = \"openrouter/auto\"\nbase_url = \"http://127.0.0.1:9/api/v1\"\nauto_code_write = false\n",
+    );
+    // Neither the OpenRouter key nor its base_url go to Anthropic
+    let out = sb.run(&["--provider", "anthropic", "hi"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(stderr.contains("API key is not set") && stderr.contains("ANTHROPIC_API_KEY"), "{}", stderr);
+
+    // An OpenAI-compatible provider needs a base_url of its own
+    let out = sb.run_env(&["--provider", "deepseek", "hi"], &[("OPENAI_API_KEY", "sk-deepseek")]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Missing base_url"), "{}", String::from_utf8_lossy(&out.stderr));
+
+    sb.run_ok(&["session", "new", "switched"]);
+    assert_eq!(sb.run_ok(&["--provider", "mock", "hi"]), "hi\n");
+    assert_eq!(sb.history()[1]["provider"], "mock");
+}
+
+#[test]
+fn test_usage_counts_calls_outside_sessions() {
+    let sb = Sandbox::new("auto_code_write = false\n");
+    sb.write("main.rs", "fn main() {}\n");
+    sb.run_ok(&["hi"]);
+    sb.run_ok(&["code", "review", "main.rs"]);
+    sb.run_ok(&["session", "new", "kept"]);
+    sb.run_ok(&["hello"]);
+
+    let report = sb.run_ok(&["usage", "--by", "session"]);
+    let row = |name: &str| report.lines().find(|l| l.contains(name)).unwrap_or_else(|| panic!("{}", report)).split_whitespace().collect::<Vec<_>>();
+    assert_eq!(row("(no session)")[2], "2");
+    assert_eq!(row("(kept)")[2], "1");
+    assert_eq!(row("TOTAL")[1], "3");
+}
+
+#[test]
+fn test_no_fallback_after_partial_stream() {
+    let sb = Sandbox::new("auto_code_write = false\n[mock]\nfixture = \"script.toml\"\nchunk_size = 4\n[retry]\nmax_attempts = 1\n\n[[fallback]]\nprovider = \"ollama\"\nbase_url = \"http://127.0.0.1:9\"\n");
+    sb.write("script.toml", "[[responses]]\nmatch = \"midway\"\ncontent = \"Half an answer\"\nstatus = 503\n\n[[responses]]\nstatus = 503\n");
+
+    // The reply broke off after printing, so it is not sent again
+    let out = sb.run(&["--stream", "midway"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(19), "{}", stderr);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "Half an answer\n");
+    assert!(stderr.contains("after partial output") && !stderr.contains("falling back"), "{}", stderr);
+
+    // Nothing printed yet: the fallback is tried
+    let out = sb.run(&["--stream", "early"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("falling back to ollama"), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+#[test]
+fn test_openrouter_env_key_is_not_sent_to_other_providers() {
+    let sb = Sandbox::with_config("provider = \"openrouter\"\nauto_code_write = false\n");
+    for (provider, var) in [("anthropic", "ANTHROPIC_API_KEY"), ("google", "GEMINI_API_KEY")] {
+        let out = sb.run_env(&["--provider", provider, "hi"], &[("OPENROUTER_API_KEY", "sk-or-env")]);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(!out.status.success());
+        assert!(stderr.contains("API key is not set") && stderr.contains(var), "{}", stderr);
+    }
+}
+
+#[test]
+fn test_openrouter_model_is_not_sent_to_other_providers() {
+    // The mock reports the model it was asked for, or `mock` for its default
+    let sb = Sandbox::with_config("model = \"openrouter/auto\"\nprovider = \"mock\"\nauto_code_write = false\n");
+    sb.run_ok(&["session", "new", "models"]);
+    sb.run_ok(&["hi"]);
+    sb.run_ok(&["--model", "tiny", "hi"]);
+    let history = sb.history();
+    assert_eq!(history[1]["model"], "mock");
+    assert_eq!(history[3]["model"], "tiny");
+
+    let sb = Sandbox::with_config("provider = \"openrouter\"\nmodel = \"anthropic/claude-3.5-sonnet\"\nauto_code_write = false\n");
+    sb.run_ok(&["session", "new", "switched"]);
+    sb.run_ok(&["--provider", "mock", "hi"]);
+    assert_eq!(sb.history()[1]["model"], "mock");
+}
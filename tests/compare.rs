@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use spark_cli::api::cassette::{CASSETTE_ENV, CASSETTE_MODE_ENV};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cassettes").join(name)
+}
+
+fn sandbox(config: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("config.toml"), format!("{}auto_code_write = false\n[retry]\nmax_attempts = 1\n", config)).unwrap();
+    dir
+}
+
+fn spark(dir: &Path, args: &[&str]) -> Output {
+    spark_with_cassette(dir, None, args)
+}
+
+/// Runs with OpenRouter traffic replayed from `cassette`, when given.
+fn spark_with_cassette(dir: &Path, cassette: Option<&str>, args: &[&str]) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_spark_cli"));
+    cmd.args(args).current_dir(dir).env("HOME", dir).env_remove(CASSETTE_MODE_ENV).env_remove("RUST_LOG");
+    match cassette {
+        Some(name) => cmd.env(CASSETTE_ENV, fixture(name)).env("OPENROUTER_API_KEY", "sk-test-secret"),
+        None => cmd.env_remove(CASSETTE_ENV),
+    };
+    cmd.output().unwrap()
+}
+
+fn history(dir: &Path) -> Vec<serde_json::Value> {
+    let sessions = dir.join(".spark_cli").join("sessions");
+    let session = std::fs::read_dir(&sessions).unwrap().map(|e| e.unwrap().path()).find(|p| p.is_dir()).unwrap();
+    let raw = std::fs::read_to_string(session.join("history.jsonl")).unwrap();
+    raw.lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+}
+
+#[test]
+fn test_compare_prints_answers_and_summary() {
+    let dir = sandbox("provider = \"mock\"\n");
+    let out = spark(dir.path(), &["compare", "--models", "first,second@mock", "--out-dir", "answers", "hello there"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("== first (mock) ==\nhello there\n"), "{}", stdout);
+    assert!(stdout.contains("== second@mock (mock) ==\nhello there\n"), "{}", stdout);
+    let table: Vec<&str> = stdout.lines().skip_while(|l| !l.starts_with("MODEL")).collect();
+    assert_eq!(table.len(), 3);
+    assert!(table[1].starts_with("first ") && table[1].contains(" ok "));
+
+    let answers = dir.path().join("answers");
+    assert_eq!(std::fs::read_to_string(answers.join("1-first.md")).unwrap(), "hello there");
+    assert_eq!(std::fs::read_to_string(answers.join("2-second_mock.md")).unwrap(), "hello there");
+    let summary: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(answers.join("summary.json")).unwrap()).unwrap();
+    assert_eq!(summary[1]["model"], "second@mock");
+    assert_eq!(summary[1]["usage"]["completion_tokens"], 3);
+}
+
+#[test]
+fn test_compare_saves_alternatives_to_session() {
+    let dir = sandbox("provider = \"mock\"\n");
+    let out = spark(dir.path(), &["compare", "--models", "a,b", "--save", "hi"]);
+    assert!(!out.status.success(), "--save without a session should fail");
+
+    assert!(spark(dir.path(), &["session", "new", "cmp"]).status.success());
+    assert!(spark(dir.path(), &["compare", "--models", "a,b", "--save", "hi"]).status.success());
+    let records = history(dir.path());
+    let roles: Vec<&str> = records.iter().map(|r| r["role"].as_str().unwrap()).collect();
+    assert_eq!(roles, ["user", "assistant", "assistant"]);
+    assert!(records[1].get("alternative").is_none());
+    assert_eq!(records[2]["alternative"], true);
+}
+
+#[test]
+fn test_compare_reports_failures_per_model() {
+    let dir = sandbox("provider = \"openrouter\"\n");
+    let cassette = Some("openrouter_rate_limited.json");
+    let out = spark_with_cassette(dir.path(), cassette, &["compare", "--models", "openrouter/auto,mock@mock", "Say hi"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("Rate limited"), "{}", stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("openrouter/auto") && l.contains(" error ")), "{}", stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("mock@mock") && l.contains(" ok ")), "{}", stdout);
+
+    // Every model failing exits with the first error's code
+    let out = spark_with_cassette(dir.path(), cassette, &["compare", "--models", "openrouter/auto", "Say hi"]);
+    assert_eq!(out.status.code(), Some(11));
+}
+
+#[test]
+fn test_compare_rejects_unknown_provider_before_sending() {
+    let dir = sandbox("provider = \"mock\"\n");
+    let out = spark(dir.path(), &["compare", "--models", "a,b@nope", "hi"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Cannot compare b@nope"));
+    assert!(out.stdout.is_empty());
+}
@@ -0,0 +1,90 @@
+use anyhow::Context;
+use reqwest::StatusCode;
+use spark_cli::errors::ProviderError;
+
+fn classify(status: u16, body: &str) -> ProviderError {
+    ProviderError::from_response("openrouter", StatusCode::from_u16(status).unwrap(), body)
+}
+
+#[test]
+fn test_classifies_openai_and_openrouter_bodies() {
+    let e = classify(401, r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error","code":"invalid_api_key"}}"#);
+    assert!(matches!(e, ProviderError::Auth(_)));
+    assert_eq!(e.info().message, "Incorrect API key provided");
+
+    let e = classify(400, r#"{"error":{"message":"This model's maximum context length is 8192 tokens","code":"context_length_exceeded"}}"#);
+    assert!(matches!(e, ProviderError::ContextLengthExceeded(_)));
+
+    let e = classify(404, r#"{"error":{"message":"The model `gpt-9` does not exist","code":"model_not_found"}}"#);
+    assert!(matches!(e, ProviderError::ModelNotFound(_)));
+
+    let e = classify(402, r#"{"error":{"code":402,"message":"This request requires more credits"}}"#);
+    assert!(matches!(e, ProviderError::InsufficientCredits(_)));
+
+    // OpenAI reports exhausted quota as a 429, which is not worth retrying as a rate limit
+    let e = classify(429, r#"{"error":{"message":"You exceeded your current quota","code":"insufficient_quota"}}"#);
+    assert!(matches!(e, ProviderError::InsufficientCredits(_)));
+
+    let e = classify(429, r#"{"error":{"code":429,"message":"Rate limit exceeded: free-models-per-min"}}"#);
+    assert!(matches!(e, ProviderError::RateLimited(_)));
+
+    // OpenRouter moderation uses 403, which must not read as an auth failure
+    let e = classify(403, r#"{"error":{"code":403,"message":"Input was flagged","metadata":{"reasons":["violence"]}}}"#);
+    assert!(matches!(e, ProviderError::ContentFiltered(_)));
+
+    let e = classify(408, "");
+    assert!(matches!(e, ProviderError::Timeout(_)));
+    assert_eq!(e.info().message, "408 Request Timeout");
+}
+
+#[test]
+fn test_classifies_other_provider_shapes() {
+    let anthropic = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+    assert!(matches!(ProviderError::from_body("anthropic", anthropic), ProviderError::Auth(_)));
+
+    let gemini = r#"{"error":{"code":400,"message":"API key not valid.","status":"INVALID_ARGUMENT"}}"#;
+    assert!(matches!(classify(400, gemini), ProviderError::Auth(_)));
+
+    let ollama = r#"{"error":"model \"llama9\" not found, try pulling it first"}"#;
+    assert!(matches!(classify(404, ollama), ProviderError::ModelNotFound(_)));
+
+    let e = classify(502, "<html>Bad Gateway</html>");
+    assert!(matches!(e, ProviderError::Other(_)));
+    assert_eq!(e.info().message, "<html>Bad Gateway</html>");
+    assert!(e.hint().is_none());
+}
+
+#[test]
+fn test_display_includes_provider_status_and_raw_upstream_error() {
+    let e = classify(400, r#"{"error":{"code":400,"message":"Provider returned error","metadata":{"raw":"bad tool schema"}}}"#);
+    assert_eq!(e.to_string(), "Provider error (openrouter, HTTP 400): Provider returned error (bad tool schema)");
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let bodies = [
+        (401, ""),
+        (429, ""),
+        (400, "context length exceeded"),
+        (404, ""),
+        (402, ""),
+        (400, "content_filter"),
+        (408, ""),
+        (500, ""),
+    ];
+    let mut codes: Vec<i32> = bodies.iter().map(|(s, b)| classify(*s, b).exit_code()).collect();
+    codes.push(ProviderError::malformed("openrouter", "no choices").exit_code());
+    let mut unique = codes.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), codes.len());
+    assert!(codes.iter().all(|c| *c > 1));
+}
+
+#[test]
+fn test_survives_context_wrapping() {
+    let err: anyhow::Result<()> = Err(classify(401, "").into());
+    let err = err.context("Request failed").unwrap_err();
+    let pe = err.downcast_ref::<ProviderError>().expect("provider error in chain");
+    assert_eq!(pe.exit_code(), 10);
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use spark_cli::api::cassette::{CASSETTE_ENV, CASSETTE_MODE_ENV};
+use spark_cli::api::fallback::FallbackTarget;
+use spark_cli::config::settings::Settings;
+use spark_cli::errors::ErrorClass;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cassettes").join(name)
+}
+
+/// Runs `spark "Say hi"` in a fresh session against an OpenRouter cassette, with
+/// `config` appended to the config file.
+fn run(dir: &Path, cassette: &str, config: &str) -> Output {
+    let base = "provider = \"openrouter\"\nauto_code_write = false\n[retry]\nmax_attempts = 1\n";
+    std::fs::write(dir.join("config.toml"), format!("{}{}", base, config)).unwrap();
+    let _ = std::fs::remove_dir_all(dir.join(".spark_cli").join("sessions"));
+    assert!(spark(dir, cassette, &["session", "new", "fallback"]).status.success());
+    spark(dir, cassette, &["Say hi"])
+}
+
+fn spark(dir: &Path, cassette: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_spark_cli"))
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("OPENROUTER_API_KEY", "sk-test-secret")
+        .env(CASSETTE_ENV, fixture(cassette))
+        .env_remove(CASSETTE_MODE_ENV)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap()
+}
+
+fn last_record(dir: &Path) -> serde_json::Value {
+    let sessions = dir.join(".spark_cli").join("sessions");
+    let session = std::fs::read_dir(&sessions).unwrap().map(|e| e.unwrap().path()).find(|p| p.is_dir()).unwrap();
+    let raw = std::fs::read_to_string(session.join("history.jsonl")).unwrap();
+    serde_json::from_str(raw.lines().last().unwrap()).unwrap()
+}
+
+#[test]
+fn test_fallback_table_parses() {
+    let settings: Settings = toml::from_str(
+        r#"
+        [[fallback]]
+        provider = "anthropic"
+        model = "claude-sonnet-4"
+
+        [[fallback]]
+        provider = "deepseek"
+        base_url = "https://api.deepseek.com/v1"
+        on = ["context_length", "server"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(settings.fallback.len(), 2);
+    assert_eq!(settings.fallback[0].model.as_deref(), Some("claude-sonnet-4"));
+    assert_eq!(settings.fallback[1].on, [ErrorClass::ContextLength, ErrorClass::Server]);
+
+    // No chain configured stays out of a saved config
+    assert!(!toml::to_string_pretty(&Settings::default()).unwrap().contains("fallback"));
+}
+
+#[test]
+fn test_default_triggers_skip_request_errors() {
+    let target = FallbackTarget { provider: "mock".into(), ..Default::default() };
+    for class in [ErrorClass::RateLimited, ErrorClass::Server, ErrorClass::Timeout, ErrorClass::Network, ErrorClass::ModelNotFound] {
+        assert!(target.handles(class), "{:?}", class);
+    }
+    for class in [ErrorClass::Auth, ErrorClass::ContextLength, ErrorClass::ContentFiltered, ErrorClass::Other] {
+        assert!(!target.handles(class), "{:?}", class);
+    }
+    let target = FallbackTarget { on: vec![ErrorClass::ContextLength], ..target };
+    assert!(target.handles(ErrorClass::ContextLength));
+    assert!(!target.handles(ErrorClass::RateLimited));
+}
+
+#[test]
+fn test_rate_limit_falls_back_and_records_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(dir.path(), "openrouter_rate_limited.json", "[[fallback]]\nprovider = \"mock\"\n");
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "Say hi\n");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("falling back to mock"), "{}", stderr);
+    assert!(stderr.contains("Answered by fallback mock"), "{}", stderr);
+
+    let record = last_record(dir.path());
+    assert_eq!(record["provider"], "mock");
+    assert_eq!(record["model"], "mock");
+}
+
+#[test]
+fn test_unhandled_errors_do_not_fall_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = run(dir.path(), "openrouter_auth_error.json", "[[fallback]]\nprovider = \"mock\"\n");
+    assert_eq!(out.status.code(), Some(10));
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("falling back"));
+
+    let out = run(dir.path(), "openrouter_rate_limited.json", "[[fallback]]\nprovider = \"mock\"\non = [\"context_length\"]\n");
+    assert_eq!(out.status.code(), Some(11));
+}
+
+#[test]
+fn test_unusable_fallback_is_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = "[[fallback]]\nprovider = \"openai-compatible\"\n\n[[fallback]]\nprovider = \"mock\"\n";
+    let out = run(dir.path(), "openrouter_rate_limited.json", config);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("Skipping fallback openai-compatible"), "{}", stderr);
+    assert_eq!(last_record(dir.path())["provider"], "mock");
+}
@@ -0,0 +1,126 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use spark_cli::api::cassette::CassetteServer;
+use spark_cli::api::client::HttpSettings;
+use spark_cli::api::models::{ChatMessage, ChatRequest, SamplingParams};
+use spark_cli::api::openrouter;
+use spark_cli::api::providers::{ChatProvider, ProviderContext};
+use spark_cli::api::retry::RetryPolicy;
+use spark_cli::config::settings::Settings;
+
+#[test]
+fn test_http_table_parses_and_defaults() {
+    let settings: Settings = toml::from_str(
+        r#"
+        [http]
+        read_timeout_secs = 300
+        proxy = "socks5h://127.0.0.1:1080"
+        user_agent = "acme-spark/1.0"
+
+        [http.headers.openrouter]
+        "HTTP-Referer" = "https://example.com/app"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(settings.http.connect_timeout_secs, HttpSettings::default().connect_timeout_secs);
+    assert_eq!(settings.http.read_timeout_secs, 300);
+    assert_eq!(settings.http.headers["openrouter"]["HTTP-Referer"], "https://example.com/app");
+    settings.http.build_client().unwrap();
+
+    // Defaults stay out of a saved config
+    let saved = toml::to_string_pretty(&Settings::default()).unwrap();
+    assert!(!saved.contains("proxy") && !saved.contains("[http.headers"));
+}
+
+#[test]
+fn test_headers_for_merges_alias_over_provider_name() {
+    let http: HttpSettings = toml::from_str(
+        r#"
+        [headers.openai-compatible]
+        X-Org = "base"
+        X-Team = "core"
+
+        [headers.DeepSeek]
+        X-Org = "override"
+        "#,
+    )
+    .unwrap();
+    let headers = http.headers_for(&["openai-compatible", "deepseek"]).unwrap();
+    assert_eq!(headers["x-org"], "override");
+    assert_eq!(headers["x-team"], "core");
+    assert!(http.headers_for(&["anthropic"]).unwrap().is_empty());
+}
+
+#[test]
+fn test_invalid_settings_are_reported() {
+    let mut http = HttpSettings::default();
+    http.headers.insert("openrouter".into(), [("bad header".to_string(), "x".to_string())].into());
+    assert!(http.headers_for(&["openrouter"]).unwrap_err().to_string().contains("bad header"));
+
+    let http = HttpSettings { proxy: Some("not a url".into()), ..Default::default() };
+    assert!(http.build_client().unwrap_err().to_string().contains("http.proxy"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing.pem");
+    let http = HttpSettings { ca_cert: Some(missing.display().to_string()), ..Default::default() };
+    assert!(http.build_client().unwrap_err().to_string().contains("Failed to read http.ca_cert"));
+
+    let empty = dir.path().join("empty.pem");
+    std::fs::write(&empty, "not a certificate\n").unwrap();
+    let http = HttpSettings { ca_cert: Some(empty.display().to_string()), ..Default::default() };
+    assert!(http.build_client().is_err());
+}
+
+#[tokio::test]
+async fn test_extra_headers_override_provider_defaults() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cassettes/openrouter_chat.json");
+    let server = CassetteServer::replay(&fixture).await.unwrap();
+    let base = server.rebase("https://openrouter.ai/api/v1");
+    let http = HttpSettings { user_agent: Some("acme-spark/1.0".into()), ..Default::default() };
+    let client = http.build_client().unwrap();
+    let mut extra = HttpSettings::default();
+    extra.headers.insert(
+        "openrouter".into(),
+        [("HTTP-Referer".to_string(), "https://example.com/app".to_string()), ("X-Title".to_string(), "Acme".to_string())].into(),
+    );
+    let headers = extra.headers_for(&["openrouter"]).unwrap();
+    let ctx = ProviderContext {
+        http: &client,
+        api_key: Some("sk-test"),
+        base_url: Some(&base),
+        retry: RetryPolicy { max_attempts: 1, ..Default::default() },
+        on_retry: None,
+        headers: Some(&headers),
+    };
+    let req = ChatRequest {
+        model: String::new(),
+        messages: vec![ChatMessage { role: "user".into(), content: "Say hi".into() }],
+        stream: None,
+        params: SamplingParams::default(),
+        stream_options: None,
+    };
+    openrouter::OpenRouter.complete(&ctx, req).await.unwrap();
+
+    let sent = &server.requests()[0].headers;
+    assert_eq!(sent["http-referer"], "https://example.com/app");
+    assert_eq!(sent["x-title"], "Acme");
+    assert_eq!(sent["user-agent"], "acme-spark/1.0");
+}
+
+#[tokio::test]
+async fn test_read_timeout_fires_on_a_stalled_response() {
+    // Accepts the connection but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _stall = tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    });
+
+    let http = HttpSettings { read_timeout_secs: 1, ..Default::default() };
+    let started = Instant::now();
+    let err = http.build_client().unwrap().get(format!("http://{}/", addr)).send().await.unwrap_err();
+    assert!(err.is_timeout(), "{:?}", err);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
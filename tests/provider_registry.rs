@@ -0,0 +1,22 @@
+use spark_cli::api::providers::ProviderRegistry;
+
+#[test]
+fn test_aliases_resolve_to_openai_compatible() {
+    let registry = ProviderRegistry::with_defaults();
+    for name in ["openai", "DeepSeek", "qwen", "openai-compatible"] {
+        assert_eq!(registry.get(name).unwrap().name(), "openai-compatible");
+    }
+    assert_eq!(registry.get("").unwrap().name(), "openrouter");
+    assert_eq!(registry.get("anthropic").unwrap().name(), "anthropic");
+    assert_eq!(registry.get("gemini").unwrap().name(), "google");
+    assert!(!registry.get("ollama").unwrap().capabilities().requires_api_key);
+    assert!(!registry.get("mock").unwrap().capabilities().requires_api_key);
+}
+
+#[test]
+fn test_unknown_provider_lists_registered_names() {
+    let registry = ProviderRegistry::with_defaults();
+    let err = registry.get("nope").err().unwrap().to_string();
+    assert!(err.contains("Unknown provider 'nope'"));
+    assert!(err.contains("openrouter"));
+}
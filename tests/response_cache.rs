@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use spark_cli::api::cache::{cache_key, CacheSettings, ResponseCache};
+use spark_cli::api::models::{ChatMessage, ChatRequest, ChatResponse, SamplingParams, Usage};
+
+fn request(prompt: &str) -> ChatRequest {
+    ChatRequest {
+        model: "openai/gpt-4o-mini".into(),
+        messages: vec![ChatMessage { role: "user".into(), content: prompt.into() }],
+        stream: None,
+        params: SamplingParams::default(),
+        stream_options: None,
+    }
+}
+
+fn enabled() -> CacheSettings { CacheSettings { enabled: true, ..Default::default() } }
+
+#[test]
+fn test_key_covers_request_content_but_not_streaming() {
+    let base = cache_key("openrouter", None, &request("hi"));
+    assert_eq!(base.len(), 64);
+    assert_eq!(base, cache_key("openrouter", None, &request("hi")));
+
+    let mut streamed = request("hi");
+    streamed.stream = Some(true);
+    assert_eq!(base, cache_key("openrouter", None, &streamed));
+
+    let mut warmer = request("hi");
+    warmer.params.temperature = Some(0.7);
+    assert_ne!(base, cache_key("openrouter", None, &warmer));
+    assert_ne!(base, cache_key("openrouter", None, &request("hello")));
+    assert_ne!(base, cache_key("anthropic", None, &request("hi")));
+    assert_ne!(base, cache_key("openrouter", Some("http://localhost:8080/v1"), &request("hi")));
+}
+
+#[test]
+fn test_put_get_roundtrip_marks_cached() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::with_dir(dir.path().to_path_buf(), enabled());
+    let key = cache_key("openrouter", None, &request("hi"));
+    assert!(cache.get(&key).is_none());
+
+    let resp = ChatResponse { content: "hello".into(), usage: Some(Usage::new(3, 1)), model: Some("m".into()), ..Default::default() };
+    cache.put(&key, "openrouter", &resp).unwrap();
+    let hit = cache.get(&key).unwrap();
+    assert!(hit.cached);
+    assert_eq!(hit.content, "hello");
+    assert_eq!(hit.usage, Some(Usage::new(3, 1)));
+
+    // Partial and empty replies are never stored
+    let other = cache_key("openrouter", None, &request("other"));
+    cache.put(&other, "openrouter", &ChatResponse { content: "par".into(), interrupted: true, ..Default::default() }).unwrap();
+    cache.put(&other, "openrouter", &ChatResponse::default()).unwrap();
+    assert!(cache.get(&other).is_none());
+    assert_eq!(cache.stats().unwrap().entries, 1);
+}
+
+#[test]
+fn test_expired_entries_are_dropped_on_lookup() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::with_dir(dir.path().to_path_buf(), CacheSettings { ttl_secs: 60, ..enabled() });
+    let key = cache_key("openrouter", None, &request("hi"));
+    cache.put(&key, "openrouter", &ChatResponse::text("hello".into())).unwrap();
+
+    // Age the entry past the TTL
+    let path = dir.path().join(format!("{}.json", key));
+    let mut entry: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    entry["created_ms"] = serde_json::json!(chrono::Utc::now().timestamp_millis() - 61_000);
+    std::fs::write(&path, entry.to_string()).unwrap();
+
+    assert_eq!(cache.stats().unwrap().expired, 1);
+    assert!(cache.get(&key).is_none());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_eviction_removes_least_recently_used() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = ResponseCache::with_dir(dir.path().to_path_buf(), CacheSettings { max_size_mb: 1, ..enabled() });
+    let big = "x".repeat(400 * 1024);
+    let keys: Vec<String> = ["a", "b", "c"].iter().map(|p| cache_key("openrouter", None, &request(p))).collect();
+
+    cache.put(&keys[0], "openrouter", &ChatResponse::text(big.clone())).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    cache.put(&keys[1], "openrouter", &ChatResponse::text(big.clone())).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+    // Touch the first entry so the second becomes least recently used
+    assert!(cache.get(&keys[0]).is_some());
+    std::thread::sleep(Duration::from_millis(20));
+    cache.put(&keys[2], "openrouter", &ChatResponse::text(big)).unwrap();
+
+    assert!(cache.get(&keys[0]).is_some());
+    assert!(cache.get(&keys[1]).is_none());
+    assert!(cache.get(&keys[2]).is_some());
+    assert_eq!(cache.clear().unwrap(), 2);
+    assert_eq!(cache.stats().unwrap().entries, 0);
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use spark_cli::api::retry::{server_delay, RetryPolicy};
+
+#[test]
+fn test_backoff_doubles_and_caps_without_jitter() {
+    let policy = RetryPolicy { max_attempts: 5, base_delay_ms: 100, max_delay_ms: 350, jitter: false };
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(350));
+}
+
+#[test]
+fn test_jitter_stays_within_half_to_full_delay() {
+    let policy = RetryPolicy { max_attempts: 3, base_delay_ms: 1000, max_delay_ms: 10_000, jitter: true };
+    for _ in 0..50 {
+        let d = policy.backoff(1);
+        assert!(d >= Duration::from_millis(500) && d <= Duration::from_millis(1000));
+    }
+}
+
+#[test]
+fn test_server_delay_headers() {
+    let mut headers = HeaderMap::new();
+    headers.insert("retry-after", HeaderValue::from_static("3"));
+    assert_eq!(server_delay(&headers), Some(Duration::from_secs(3)));
+
+    let mut headers = HeaderMap::new();
+    let reset_ms = chrono::Utc::now().timestamp_millis() + 5_000;
+    headers.insert("x-ratelimit-reset", HeaderValue::from_str(&reset_ms.to_string()).unwrap());
+    let d = server_delay(&headers).unwrap();
+    assert!(d > Duration::from_secs(3) && d <= Duration::from_secs(5));
+
+    assert_eq!(server_delay(&HeaderMap::new()), None);
+}
@@ -0,0 +1,32 @@
+use spark_cli::api::models::{ChatMessage, ChatRequest, SamplingParams};
+use spark_cli::config::settings::Settings;
+
+#[test]
+fn test_command_table_overrides_global_sampling() {
+    let settings: Settings = toml::from_str(
+        "[sampling]\ntemperature = 0.9\nmax_tokens = 512\n\n[sampling.commands.generate]\ntemperature = 0.0\n",
+    )
+    .unwrap();
+    let gen = settings.sampling_for("generate");
+    assert_eq!(gen.temperature, Some(0.0));
+    assert_eq!(gen.max_tokens, Some(512));
+    assert_eq!(settings.sampling_for("chat").temperature, Some(0.9));
+
+    let cli = SamplingParams { temperature: Some(0.3), ..Default::default() };
+    assert_eq!(gen.merged(&cli).temperature, Some(0.3));
+}
+
+#[test]
+fn test_request_serializes_only_set_params() {
+    let req = ChatRequest {
+        model: "m".into(),
+        messages: vec![ChatMessage { role: "user".into(), content: "hi".into() }],
+        stream: None,
+        params: SamplingParams { seed: Some(7), stop: Some(vec!["END".into()]), ..Default::default() },
+        stream_options: None,
+    };
+    let value = serde_json::to_value(&req).unwrap();
+    assert_eq!(value["seed"], 7);
+    assert_eq!(value["stop"][0], "END");
+    assert!(value.get("temperature").is_none());
+}
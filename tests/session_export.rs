@@ -0,0 +1,82 @@
+use spark_cli::api::models::Usage;
+use spark_cli::session::export::{highlight, render, segments, ExportFormat, Segment};
+use spark_cli::session::history::MessageRecord;
+use spark_cli::session::manager::SessionMeta;
+
+fn fixture() -> (SessionMeta, Vec<MessageRecord>) {
+    let meta = SessionMeta { id: "1700000000000".into(), name: "Rust <help>".into(), created_ms: 1_700_000_000_000, ..Default::default() };
+    let records = vec![
+        MessageRecord { role: "user".into(), content: "How do I print?".into(), timestamp_ms: 1_700_000_001_000, ..Default::default() },
+        MessageRecord {
+            role: "assistant".into(),
+            content: "Use `println!`:\n\n```rust\nfn main() {\n    println!(\"hi <3\"); // greet\n}\n```\nDone.".into(),
+            timestamp_ms: 1_700_000_002_000,
+            provider: Some("openrouter".into()),
+            model: Some("openai/gpt-4o-mini".into()),
+            usage: Some(Usage::new(10, 20)),
+            cost_usd: Some(0.0012),
+            ..Default::default()
+        },
+    ];
+    (meta, records)
+}
+
+#[test]
+fn test_markdown_keeps_code_and_metadata() {
+    let (meta, records) = fixture();
+    let md = render(&meta, &records, ExportFormat::Markdown).unwrap();
+    assert!(md.starts_with("# Rust <help>\n"));
+    assert!(md.contains("- **Created:** 2023-11-14 22:13:20 UTC"));
+    assert!(md.contains("- **Tokens:** 30 (10 prompt, 20 completion)"));
+    assert!(md.contains("### Assistant · 2023-11-14 22:13:22 UTC"));
+    assert!(md.contains("*openrouter / openai/gpt-4o-mini · 30 tokens · $0.0012*"));
+    assert!(md.contains("```rust\nfn main() {\n    println!(\"hi <3\"); // greet\n}\n```"));
+}
+
+#[test]
+fn test_html_is_escaped_and_highlighted() {
+    let (meta, records) = fixture();
+    let html = render(&meta, &records, ExportFormat::Html).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(html.contains("<title>Rust &lt;help&gt;</title>"));
+    assert!(html.contains("<p>Use <code>println!</code>:</p>"));
+    assert!(html.contains("<span class=\"lang\">rust</span>"));
+    assert!(html.contains("<span class=\"kw\">fn</span> main()"));
+    assert!(html.contains("<span class=\"str\">&quot;hi &lt;3&quot;</span>"));
+    assert!(html.contains("<span class=\"com\">// greet</span>"));
+    assert!(!html.contains("<3"));
+}
+
+#[test]
+fn test_json_and_jsonl_round_trip() {
+    let (meta, records) = fixture();
+    let json: serde_json::Value = serde_json::from_str(&render(&meta, &records, ExportFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["session"]["name"], "Rust <help>");
+    assert_eq!(json["messages"][1]["usage"]["total_tokens"], 30);
+
+    let jsonl = render(&meta, &records, ExportFormat::Jsonl).unwrap();
+    let lines: Vec<&str> = jsonl.lines().collect();
+    assert_eq!(lines.len(), 3);
+    let header: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(header["session"]["id"], "1700000000000");
+    let record: MessageRecord = serde_json::from_str(lines[2]).unwrap();
+    assert_eq!(record.content, records[1].content);
+}
+
+#[test]
+fn test_segments_and_highlight_edge_cases() {
+    let parts = segments("intro\n```\nplain\n```\n```py\nx = 1  # one\n```");
+    assert_eq!(
+        parts,
+        [
+            Segment::Text("intro\n"),
+            Segment::Code { lang: "", body: "plain\n" },
+            Segment::Text("\n"),
+            Segment::Code { lang: "py", body: "x = 1  # one\n" },
+        ]
+    );
+    assert_eq!(highlight("py", "x = 1  # one"), "x = <span class=\"num\">1</span>  <span class=\"com\"># one</span>");
+    // Lifetimes are not strings
+    assert_eq!(highlight("rust", "&'a str"), "&amp;&#39;a str");
+}
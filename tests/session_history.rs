@@ -0,0 +1,123 @@
+use spark_cli::session::history::MessageRecord;
+use spark_cli::session::manager::SessionManager;
+
+#[test]
+fn test_history_replays_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("demo").unwrap();
+    for (role, content) in [("user", "hi"), ("assistant", "hello"), ("user", "and again?")] {
+        mgr.append_message(&id, &MessageRecord { role: role.into(), content: content.into(), timestamp_ms: 1, ..Default::default() }).unwrap();
+    }
+    let messages = mgr.load_context(&id).unwrap();
+    let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, ["user", "assistant", "user"]);
+    assert_eq!(messages[1].content, "hello");
+}
+
+#[test]
+fn test_history_skips_malformed_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("demo").unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "user".into(), content: "ok".into(), timestamp_ms: 1, ..Default::default() }).unwrap();
+    let path = dir.path().join(&id).join("history.jsonl");
+    let mut content = std::fs::read_to_string(&path).unwrap();
+    content.push_str("not json\n");
+    std::fs::write(&path, content).unwrap();
+    assert_eq!(mgr.load_history(&id).unwrap().len(), 1);
+}
+
+#[test]
+fn test_interrupted_flag_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("demo").unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "assistant".into(), content: "partial".into(), timestamp_ms: 1, interrupted: true, ..Default::default() }).unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "assistant".into(), content: "done".into(), timestamp_ms: 2, ..Default::default() }).unwrap();
+
+    let raw = std::fs::read_to_string(dir.path().join(&id).join("history.jsonl")).unwrap();
+    let lines: Vec<&str> = raw.lines().collect();
+    assert!(lines[0].contains("\"interrupted\":true"));
+    assert!(!lines[1].contains("interrupted"));
+
+    let records = mgr.load_history(&id).unwrap();
+    assert!(records[0].interrupted);
+    assert!(!records[1].interrupted);
+}
+
+#[test]
+fn test_alternatives_are_not_replayed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("demo").unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "user".into(), content: "q".into(), timestamp_ms: 1, ..Default::default() }).unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "assistant".into(), content: "a".into(), timestamp_ms: 2, ..Default::default() }).unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "assistant".into(), content: "b".into(), timestamp_ms: 2, alternative: true, ..Default::default() }).unwrap();
+
+    assert_eq!(mgr.load_history(&id).unwrap().len(), 3);
+    let contents: Vec<String> = mgr.load_context(&id).unwrap().into_iter().map(|m| m.content).collect();
+    assert_eq!(contents, ["q", "a"]);
+}
+
+#[test]
+fn test_fork_copies_prefix_and_records_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("main").unwrap();
+    for content in ["one", "two", "three"] {
+        mgr.append_message(&id, &MessageRecord { role: "user".into(), content: content.into(), timestamp_ms: 1, ..Default::default() }).unwrap();
+    }
+    let fork = mgr.fork_session(&id, 2, None).unwrap();
+    assert_ne!(fork.id, id);
+    assert_eq!(fork.name, "main (fork)");
+    let meta = mgr.load_meta(&fork.id).unwrap();
+    assert_eq!((meta.parent.as_deref(), meta.fork_at), (Some(id.as_str()), Some(2)));
+    let contents: Vec<String> = mgr.load_history(&fork.id).unwrap().into_iter().map(|r| r.content).collect();
+    assert_eq!(contents, ["one", "two"]);
+    assert_eq!(mgr.load_history(&id).unwrap().len(), 3);
+
+    assert!(mgr.fork_session(&id, 4, None).is_err());
+    assert!(mgr.fork_session(&id, 0, None).is_err());
+    assert!(mgr.fork_session("missing", 1, None).is_err());
+}
+
+#[test]
+fn test_meta_updates_and_activity_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("demo").unwrap();
+    let created = mgr.load_meta(&id).unwrap();
+    assert_eq!(created.updated_ms, created.created_ms);
+
+    let later = created.created_ms + 60_000;
+    mgr.append_message(&id, &MessageRecord { role: "user".into(), content: "hi".into(), timestamp_ms: later, ..Default::default() }).unwrap();
+    assert_eq!(mgr.load_meta(&id).unwrap().last_active_ms(), later);
+
+    // A newer but idle session lists after the one just written to
+    let idle = mgr.create_session("idle").unwrap();
+    let order: Vec<String> = mgr.list_sessions().unwrap().into_iter().map(|m| m.id).collect();
+    assert_eq!(order, [id.clone(), idle.clone()]);
+
+    mgr.update_meta(&id, |m| {
+        m.tags.push("rust".into());
+        m.system = Some("Answer in haiku.".into());
+        m.model = Some("openai/gpt-4o".into());
+        Ok(())
+    })
+    .unwrap();
+    let meta = mgr.load_meta(&id).unwrap();
+    assert_eq!(meta.tags, ["rust"]);
+    assert_eq!(meta.system.as_deref(), Some("Answer in haiku."));
+    assert!(mgr.update_meta(&id, |_| Err(anyhow::anyhow!("nope"))).is_err());
+    assert_eq!(mgr.load_meta(&id).unwrap().tags, ["rust"]);
+
+    let fork = mgr.fork_session(&id, 1, None).unwrap();
+    assert_eq!((fork.tags, fork.model), (meta.tags, meta.model));
+
+    // Metadata written before these fields existed still loads
+    std::fs::write(dir.path().join(&id).join("meta.json"), format!(r#"{{"id":"{}","name":"old","created_ms":5}}"#, id)).unwrap();
+    let old = mgr.load_meta(&id).unwrap();
+    assert_eq!((old.updated_ms, old.last_active_ms()), (0, 5));
+    assert!(old.tags.is_empty() && old.system.is_none());
+}
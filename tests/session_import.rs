@@ -0,0 +1,132 @@
+use spark_cli::session::export::{render, ExportFormat};
+use spark_cli::session::history::MessageRecord;
+use spark_cli::session::import::{parse, ImportSource};
+use spark_cli::session::manager::{SessionManager, SessionMeta};
+
+const NOW: i64 = 1_800_000_000_000;
+
+#[test]
+fn test_chatgpt_imports_active_branch() {
+    let content = std::fs::read_to_string("tests/fixtures/import/chatgpt_conversations.json").unwrap();
+    let sessions = parse(ImportSource::ChatGpt, &content, "conversations", NOW).unwrap();
+    assert_eq!(sessions.len(), 2);
+    let s = &sessions[0];
+    assert_eq!(s.meta.name, "Borrow checker help");
+    assert_eq!(s.meta.created_ms, 1_700_000_000_500);
+    let roles: Vec<&str> = s.records.iter().map(|r| r.role.as_str()).collect();
+    assert_eq!(roles, ["user", "assistant", "user", "assistant"]);
+    assert_eq!(s.records[0].content, "Why does this not compile?");
+    assert_eq!(s.records[0].timestamp_ms, 1_700_000_010_000);
+    assert_eq!(s.records[1].content, "You move `v` and then use it.");
+    assert_eq!(s.records[1].model.as_deref(), Some("gpt-4o"));
+    assert_eq!(s.records[3].content, "```rust\nlet w = v.clone();\n```");
+    assert!(s.records.iter().all(|r| !r.content.contains("regenerated")));
+    assert_eq!(s.skipped.get("image part"), Some(&1));
+    assert_eq!(s.skipped.get("tool message"), Some(&1));
+    assert!(sessions[1].records.is_empty());
+}
+
+#[test]
+fn test_chatgpt_without_create_time_is_dated_by_messages() {
+    let content = r#"[
+        {"title": "Dated", "current_node": "b", "mapping": {
+            "a": {"message": {"author": {"role": "user"}, "content": {"content_type": "text", "parts": ["hi"]}}},
+            "b": {"parent": "a", "message": {"author": {"role": "assistant"}, "create_time": 1700000100.0, "content": {"content_type": "text", "parts": ["hello"]}}}
+        }},
+        {"title": "Undated", "current_node": "a", "mapping": {
+            "a": {"message": {"author": {"role": "user"}, "content": {"content_type": "text", "parts": ["hi"]}}}
+        }}
+    ]"#;
+    let sessions = parse(ImportSource::ChatGpt, content, "conversations", NOW).unwrap();
+    assert_eq!(sessions[0].meta.created_ms, 1_700_000_100_000);
+    assert_eq!(sessions[1].meta.created_ms, NOW);
+    assert_eq!(sessions[1].records[0].timestamp_ms, NOW);
+}
+
+#[test]
+fn test_openai_messages_keep_text_parts() {
+    let content = r#"{"messages": [
+        {"role": "developer", "content": "Be brief."},
+        {"role": "user", "content": [{"type": "text", "text": "What is this?"}, {"type": "image_url", "image_url": {"url": "data:"}}]},
+        {"role": "assistant", "content": null, "tool_calls": [{"id": "c1"}]},
+        {"role": "tool", "content": "42", "tool_call_id": "c1"},
+        {"role": "assistant", "content": "A cat."}
+    ]}"#;
+    let sessions = parse(ImportSource::OpenAiMessages, content, "chat", NOW).unwrap();
+    let s = &sessions[0];
+    assert_eq!(s.meta.name, "chat");
+    assert_eq!(s.meta.created_ms, NOW);
+    let pairs: Vec<(&str, &str)> = s.records.iter().map(|r| (r.role.as_str(), r.content.as_str())).collect();
+    assert_eq!(pairs, [("system", "Be brief."), ("user", "What is this?"), ("assistant", "A cat.")]);
+    assert_eq!(s.skipped.get("image_url part"), Some(&1));
+    assert_eq!(s.skipped.get("tool call"), Some(&1));
+    assert_eq!(s.skipped.get("tool message"), Some(&1));
+
+    let bare = r#"[{"role": "user", "content": "hi"}]"#;
+    assert_eq!(parse(ImportSource::OpenAiMessages, bare, "chat", NOW).unwrap()[0].records.len(), 1);
+    assert!(parse(ImportSource::OpenAiMessages, r#"{"foo": 1}"#, "chat", NOW).is_err());
+}
+
+#[test]
+fn test_spark_jsonl_round_trips_export() {
+    let meta = SessionMeta { id: "1700000000000".into(), name: "Original".into(), created_ms: 1_700_000_000_000, ..Default::default() };
+    let records = vec![
+        MessageRecord { role: "user".into(), content: "hi".into(), timestamp_ms: 1_700_000_001_000, ..Default::default() },
+        MessageRecord { role: "assistant".into(), content: "hello".into(), timestamp_ms: 1_700_000_002_000, pinned: true, ..Default::default() },
+    ];
+    let jsonl = render(&meta, &records, ExportFormat::Jsonl).unwrap();
+    let s = &parse(ImportSource::SparkJsonl, &jsonl, "file", NOW).unwrap()[0];
+    assert_eq!((s.meta.name.as_str(), s.meta.created_ms), ("Original", 1_700_000_000_000));
+    assert_eq!(s.records.len(), 2);
+    assert!(s.records[1].pinned);
+
+    // The header is found after leading blank lines too
+    let s = &parse(ImportSource::SparkJsonl, &format!("\n{}", jsonl), "file", NOW).unwrap()[0];
+    assert_eq!((s.meta.name.as_str(), s.records.len()), ("Original", 2));
+
+    // A bare history.jsonl: named after the file, dated by its first message
+    let bare: String = records.iter().map(|r| serde_json::to_string(r).unwrap() + "\n").collect();
+    let s = &parse(ImportSource::SparkJsonl, &bare, "file", NOW).unwrap()[0];
+    assert_eq!((s.meta.name.as_str(), s.meta.created_ms), ("file", 1_700_000_001_000));
+}
+
+#[test]
+fn test_spark_jsonl_import_keeps_tags_and_settings() {
+    let meta = SessionMeta {
+        id: "1700000000000".into(),
+        name: "Tokio".into(),
+        created_ms: 1_700_000_000_000,
+        updated_ms: 1_700_000_900_000,
+        parent: Some("1600000000000".into()),
+        fork_at: Some(2),
+        description: Some("Migration notes".into()),
+        tags: vec!["rust".into(), "async".into()],
+        provider: Some("anthropic".into()),
+        model: Some("claude-sonnet-4".into()),
+        system: Some("Be brief.".into()),
+    };
+    let records = vec![MessageRecord { role: "user".into(), content: "hi".into(), timestamp_ms: 1_700_000_001_000, ..Default::default() }];
+    let jsonl = render(&meta, &records, ExportFormat::Jsonl).unwrap();
+    let imported = parse(ImportSource::SparkJsonl, &jsonl, "file", NOW).unwrap().remove(0);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.import_session(imported.meta, &imported.records).unwrap();
+    let loaded = mgr.load_meta(&id).unwrap();
+    assert_ne!(loaded.id, meta.id);
+    assert_eq!(serde_json::to_value(SessionMeta { id: meta.id.clone(), ..loaded }).unwrap(), serde_json::to_value(&meta).unwrap());
+}
+
+#[test]
+fn test_import_session_keeps_dates_and_unique_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let records = vec![MessageRecord { role: "user".into(), content: "old".into(), timestamp_ms: 5, ..Default::default() }];
+    let meta = |name: &str, created_ms| SessionMeta { name: name.into(), created_ms, ..Default::default() };
+    let a = mgr.import_session(meta("first", 1_000), &records).unwrap();
+    let b = mgr.import_session(meta("second", 2_000), &records).unwrap();
+    assert_ne!(a, b);
+    let listed = mgr.list_sessions().unwrap();
+    assert_eq!(listed.iter().map(|m| (m.name.as_str(), m.created_ms)).collect::<Vec<_>>(), [("second", 2_000), ("first", 1_000)]);
+    assert_eq!(mgr.load_history(&a).unwrap()[0].timestamp_ms, 5);
+}
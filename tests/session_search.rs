@@ -0,0 +1,62 @@
+use spark_cli::session::history::MessageRecord;
+use spark_cli::session::manager::{SessionManager, SessionMeta};
+use spark_cli::session::search::{pattern, search, snippet, SearchFilter};
+use spark_cli::session::usage::day_of;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const T0: i64 = 1_700_000_000_000;
+
+fn sandbox() -> (tempfile::TempDir, SessionManager) {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let meta = |name: &str, created_ms| SessionMeta { name: name.into(), created_ms, ..Default::default() };
+    let rust = mgr.import_session(meta("rust", T0), &[
+        MessageRecord { role: "user".into(), content: "How does tokio::select! work?".into(), timestamp_ms: T0, ..Default::default() },
+        MessageRecord { role: "assistant".into(), content: "`tokio::select!` waits on several futures.".into(), timestamp_ms: T0 + 1000, ..Default::default() },
+    ]).unwrap();
+    mgr.import_session(meta("later", T0 + 3 * DAY_MS), &[
+        MessageRecord { role: "user".into(), content: "Cancel a Tokio task".into(), timestamp_ms: T0 + 3 * DAY_MS, ..Default::default() },
+    ]).unwrap();
+    assert!(!rust.is_empty());
+    (dir, mgr)
+}
+
+#[test]
+fn test_search_is_case_insensitive_and_newest_first() {
+    let (_dir, mgr) = sandbox();
+    let hits = search(&mgr, &pattern("tokio", false, false).unwrap(), &SearchFilter::default()).unwrap();
+    let found: Vec<(&str, usize)> = hits.iter().map(|h| (h.session.name.as_str(), h.number)).collect();
+    assert_eq!(found, [("later", 1), ("rust", 2), ("rust", 1)]);
+    assert_eq!(&hits[0].record.content[hits[0].range.clone()], "Tokio");
+
+    let exact = search(&mgr, &pattern("tokio", false, true).unwrap(), &SearchFilter::default()).unwrap();
+    assert_eq!(exact.len(), 2);
+    // Literal queries do not treat `!` or `::` specially; regex queries do
+    assert_eq!(search(&mgr, &pattern("select!", false, false).unwrap(), &SearchFilter::default()).unwrap().len(), 2);
+    let re = pattern(r"tokio::\w+!", true, false).unwrap();
+    assert_eq!(search(&mgr, &re, &SearchFilter::default()).unwrap().len(), 2);
+    assert!(pattern("(", true, false).is_err());
+}
+
+#[test]
+fn test_search_filters_role_and_dates() {
+    let (_dir, mgr) = sandbox();
+    let re = pattern("tokio", false, false).unwrap();
+    let role = SearchFilter { role: Some("assistant".into()), ..Default::default() };
+    assert_eq!(search(&mgr, &re, &role).unwrap().len(), 1);
+    let since = SearchFilter { since: Some(day_of(T0 + 2 * DAY_MS)), ..Default::default() };
+    assert_eq!(search(&mgr, &re, &since).unwrap()[0].session.name, "later");
+    let until = SearchFilter { until: Some(day_of(T0)), ..Default::default() };
+    assert!(search(&mgr, &re, &until).unwrap().iter().all(|h| h.session.name == "rust"));
+}
+
+#[test]
+fn test_snippet_cuts_context_to_one_line() {
+    let content = "first line\nsecond line mentions tokio select here and then goes on for a while";
+    let start = content.find("tokio").unwrap();
+    let (before, matched, after) = snippet(content, start..start + 5, 12);
+    assert_eq!(before, "…ne mentions ");
+    assert_eq!(matched, "tokio");
+    assert_eq!(after, " select here…");
+    assert_eq!(snippet("say tokio", 4..9, 12), ("say ".to_string(), "tokio".to_string(), String::new()));
+}
@@ -0,0 +1,107 @@
+use futures_util::stream;
+use spark_cli::api::sse::{parse_chat_event, read_chat_stream, SseDecoder, SseEvent};
+use spark_cli::errors::ProviderError;
+
+const OPENROUTER_CHAT: &[u8] = include_bytes!("fixtures/sse/openrouter_chat.sse");
+const OPENAI_CRLF: &[u8] = include_bytes!("fixtures/sse/openai_crlf_multiline.sse");
+const MIDSTREAM_ERROR: &[u8] = include_bytes!("fixtures/sse/openrouter_midstream_error.sse");
+const ANTHROPIC: &[u8] = include_bytes!("fixtures/sse/anthropic_messages.sse");
+
+fn decode_in_chunks(bytes: &[u8], size: usize) -> Vec<SseEvent> {
+    let mut decoder = SseDecoder::new();
+    let mut events: Vec<SseEvent> = bytes.chunks(size).flat_map(|c| decoder.feed(c)).collect();
+    events.extend(decoder.finish());
+    events
+}
+
+async fn read_fixture(bytes: &'static [u8], chunk: usize) -> (Result<spark_cli::api::models::ChatResponse, ProviderError>, String) {
+    let chunks: Vec<reqwest::Result<&[u8]>> = bytes.chunks(chunk).map(Ok).collect();
+    let mut seen = String::new();
+    let mut on_chunk = |s: &str| seen.push_str(s);
+    let result = read_chat_stream("openrouter", stream::iter(chunks), &mut on_chunk).await;
+    (result, seen)
+}
+
+#[test]
+fn test_chunking_does_not_change_events() {
+    for fixture in [OPENROUTER_CHAT, OPENAI_CRLF, MIDSTREAM_ERROR, ANTHROPIC] {
+        let whole = decode_in_chunks(fixture, fixture.len());
+        for size in [1, 2, 3, 7, 64] {
+            assert_eq!(decode_in_chunks(fixture, size), whole, "chunk size {}", size);
+        }
+    }
+}
+
+#[test]
+fn test_comments_are_skipped() {
+    let events = decode_in_chunks(OPENROUTER_CHAT, 16);
+    assert_eq!(events.len(), 5);
+    assert_eq!(events.last().unwrap().data, "[DONE]");
+}
+
+#[test]
+fn test_crlf_multiline_data_and_fields() {
+    let events = decode_in_chunks(OPENAI_CRLF, 5);
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].event.as_deref(), Some("message"));
+    assert_eq!(events[0].id.as_deref(), Some("1"));
+    assert!(events[0].data.contains("\"delta\":\n{"));
+    // `event` resets per event, `id` persists
+    assert_eq!(events[1].event, None);
+    assert_eq!(events[2].id.as_deref(), Some("2"));
+}
+
+#[test]
+fn test_event_names_are_kept() {
+    let names: Vec<_> = decode_in_chunks(ANTHROPIC, 9).into_iter().map(|e| e.event.unwrap_or_default()).collect();
+    assert_eq!(names, ["message_start", "ping", "content_block_delta", "error"]);
+}
+
+#[test]
+fn test_cr_only_and_unterminated_final_event() {
+    let events = decode_in_chunks(b"data: a\rdata: b\r\rdata: tail", 1);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].data, "a\nb");
+    assert_eq!(events[1].data, "tail");
+}
+
+#[test]
+fn test_field_without_value_and_bom() {
+    let events = decode_in_chunks(b"\xEF\xBB\xBFdata\n\ndata:x\n\n", 2);
+    assert_eq!(events.iter().map(|e| e.data.as_str()).collect::<Vec<_>>(), ["", "x"]);
+}
+
+#[tokio::test]
+async fn test_read_chat_stream_collects_text_usage_and_finish_reason() {
+    let (resp, seen) = read_fixture(OPENROUTER_CHAT, 11).await;
+    let resp = resp.unwrap();
+    assert_eq!(resp.content, "Hello, world!");
+    assert_eq!(seen, resp.content);
+    assert_eq!(resp.model.as_deref(), Some("anthropic/claude-3.5-sonnet"));
+    assert_eq!(resp.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(resp.usage.unwrap().total_tokens, 16);
+}
+
+#[tokio::test]
+async fn test_read_chat_stream_reports_length_finish() {
+    let (resp, _) = read_fixture(OPENAI_CRLF, 4).await;
+    let resp = resp.unwrap();
+    assert_eq!(resp.content, "Line one\nline two");
+    assert!(resp.truncated());
+}
+
+#[tokio::test]
+async fn test_midstream_error_is_surfaced() {
+    let (resp, seen) = read_fixture(MIDSTREAM_ERROR, 32).await;
+    assert_eq!(seen, "Partial ans");
+    match resp {
+        Err(ProviderError::RateLimited(info)) => assert_eq!(info.message, "Rate limit exceeded upstream"),
+        other => panic!("expected rate limit error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_error_event_without_json() {
+    let event = SseEvent { event: Some("error".into()), data: "upstream closed".into(), id: None };
+    assert!(parse_chat_event("openai-compatible", &event).is_err());
+}
@@ -0,0 +1,71 @@
+use spark_cli::api::models::{ChatMessage, ModelInfo};
+use spark_cli::api::tokens::{estimate_messages, estimate_tokens, fit, ContextBudget, ContextSettings};
+
+fn msg(role: &str, content: &str) -> ChatMessage {
+    ChatMessage { role: role.into(), content: content.into() }
+}
+
+#[test]
+fn test_estimates_track_bpe_counts() {
+    assert_eq!(estimate_tokens(""), 0);
+    // cl100k: "Hello", ",", " world", "!"
+    assert_eq!(estimate_tokens("Hello, world!"), 4);
+    assert_eq!(estimate_tokens("你好世界"), 4);
+    let code = "fn main() {\n    let total: u64 = (1..=10).sum();\n    println!(\"{}\", total);\n}\n";
+    // 33 tokens with cl100k; the estimate may run a little high but not low
+    let n = estimate_tokens(code);
+    assert!((33..=45).contains(&n), "{}", n);
+    let prose = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+    let n = estimate_tokens(&prose);
+    assert!((1000..=1200).contains(&n), "{}", n);
+    assert_eq!(estimate_messages(&[msg("user", "Hello, world!")]), 4 + 1 + 4 + 3);
+}
+
+#[test]
+fn test_budget_prefers_catalog_context_length() {
+    let models = vec![ModelInfo { id: "big".into(), context_length: Some(128_000), ..Default::default() }];
+    let settings = ContextSettings::default();
+    let budget = settings.budget(Some(&models), "big", Some(4000)).unwrap();
+    assert_eq!((budget.context_length, budget.available()), (128_000, 124_000));
+    assert!(settings.budget(Some(&models), "unknown", None).is_none());
+    assert!(settings.budget(None, "big", None).is_none());
+
+    let settings = ContextSettings { context_length: Some(8192), ..Default::default() };
+    assert_eq!(settings.budget(None, "unknown", None).unwrap().available(), 8192 - 1024);
+}
+
+#[test]
+fn test_fit_drops_oldest_turns_first() {
+    let long = "word ".repeat(40);
+    let messages = vec![
+        msg("system", "Be brief."),
+        msg("user", &long),
+        msg("assistant", &long),
+        msg("user", &long),
+        msg("assistant", &long),
+        msg("user", "latest question"),
+    ];
+    let budget = ContextBudget { context_length: 150, reserve: 0 };
+    let fitted = fit(messages.clone(), &[], &budget);
+    let contents: Vec<&str> = fitted.messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(fitted.dropped, 2);
+    assert_eq!(contents, ["Be brief.", long.as_str(), long.as_str(), "latest question"]);
+    assert_eq!(fitted.messages[1].role, "user");
+    assert!(!fitted.over(&budget));
+    assert_eq!(fitted.estimated, estimate_messages(&fitted.messages));
+
+    // Everything fits: nothing dropped
+    let roomy = ContextBudget { context_length: 10_000, reserve: 0 };
+    assert_eq!(fit(messages.clone(), &[], &roomy).dropped, 0);
+}
+
+#[test]
+fn test_fit_keeps_pinned_and_reports_overflow() {
+    let long = "word ".repeat(40);
+    let messages = vec![msg("user", "pinned fact"), msg("assistant", &long), msg("user", &long), msg("assistant", &long), msg("user", &long)];
+    let budget = ContextBudget { context_length: 50, reserve: 0 };
+    let fitted = fit(messages, &[true, false, false, false, false], &budget);
+    let contents: Vec<&str> = fitted.messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, ["pinned fact", long.as_str()]);
+    assert!(fitted.over(&budget));
+}
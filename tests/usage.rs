@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use spark_cli::api::models::{ModelInfo, ModelPricing, Usage};
+use spark_cli::api::pricing::{ModelPrice, PriceTable};
+use spark_cli::session::history::MessageRecord;
+use spark_cli::session::manager::{SessionManager, SessionMeta};
+use spark_cli::session::usage::{summarize, GroupBy, UsageLog, NO_SESSION};
+
+#[test]
+fn test_price_table_converts_openrouter_pricing_and_strips_vendor() {
+    let models = vec![ModelInfo {
+        id: "deepseek/deepseek-chat".into(),
+        pricing: Some(ModelPricing { prompt: Some("0.000001".into()), completion: Some("0.000002".into()) }),
+        ..Default::default()
+    }];
+    let table = PriceTable::from_models(&models);
+    let cost = table.cost("deepseek-chat", &Usage::new(1_000_000, 500_000)).unwrap();
+    assert!((cost - 2.0).abs() < 1e-9);
+
+    let configured = PriceTable::new(BTreeMap::from([("m".to_string(), ModelPrice { prompt: 3.0, completion: 15.0 })]));
+    assert!(configured.cost("other", &Usage::new(1, 1)).is_none());
+}
+
+#[test]
+fn test_summarize_groups_by_model_and_counts_unpriced() {
+    let meta = SessionMeta { id: "1".into(), name: "demo".into(), created_ms: 0, ..Default::default() };
+    let records = [
+        MessageRecord { role: "user".into(), content: "q".into(), ..Default::default() },
+        MessageRecord { role: "assistant".into(), model: Some("a".into()), usage: Some(Usage::new(10, 5)), cost_usd: Some(0.5), ..Default::default() },
+        MessageRecord { role: "assistant".into(), model: Some("a".into()), usage: Some(Usage::new(1, 1)), ..Default::default() },
+    ];
+    let rows = summarize(records.iter().map(|r| (&meta, r)), GroupBy::Model);
+    let a = rows["a"];
+    assert_eq!(a.requests, 2);
+    assert_eq!(a.total_tokens, 17);
+    assert_eq!(a.unpriced, 1);
+    assert!((a.cost_usd - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_fork_does_not_count_parent_usage_twice() {
+    let dir = tempfile::tempdir().unwrap();
+    let mgr = SessionManager::with_root(dir.path());
+    let id = mgr.create_session("main").unwrap();
+    mgr.append_message(&id, &MessageRecord { role: "user".into(), content: "q".into(), ..Default::default() }).unwrap();
+    let reply = MessageRecord { role: "assistant".into(), content: "a".into(), model: Some("m".into()), usage: Some(Usage::new(3, 1)), cost_usd: Some(0.25), ..Default::default() };
+    mgr.append_message(&id, &reply).unwrap();
+    let totals = |mgr: &SessionManager| {
+        let sessions: Vec<_> = mgr.list_sessions().unwrap().into_iter().map(|m| { let h = mgr.load_history(&m.id).unwrap(); (m, h) }).collect();
+        let rows = summarize(sessions.iter().flat_map(|(m, h)| h.iter().map(move |r| (m, r))), GroupBy::Model);
+        let m = rows["m"];
+        (m.requests, m.total_tokens, m.cost_usd)
+    };
+    let before = totals(&mgr);
+    assert_eq!(before, (1, 4, 0.25));
+
+    let fork = mgr.fork_session(&id, 2, None).unwrap();
+    assert_eq!(totals(&mgr), before);
+    let copied = mgr.load_history(&fork.id).unwrap();
+    assert_eq!(copied[1].content, "a");
+    assert!(copied[1].usage.is_none() && copied[1].cost_usd.is_none());
+}
+
+#[test]
+fn test_usage_log_keeps_usage_without_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = UsageLog::with_path(dir.path().join("nested").join("usage.jsonl"));
+    assert!(log.load().unwrap().is_empty());
+    let reply = MessageRecord { role: "assistant".into(), content: "secret answer".into(), model: Some("a".into()), usage: Some(Usage::new(3, 2)), ..Default::default() };
+    log.append(&reply).unwrap();
+    log.append(&MessageRecord { usage: None, ..reply.clone() }).unwrap();
+
+    let records = log.load().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].content, "");
+    let meta = SessionMeta::default();
+    let rows = summarize(records.iter().map(|r| (&meta, r)), GroupBy::Session);
+    assert_eq!(rows[NO_SESSION].total_tokens, 5);
+}